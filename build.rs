@@ -0,0 +1,27 @@
+use std::env;
+use std::process::Command;
+
+use chrono::{DateTime, Utc};
+
+// Bakes the commit and build date into `attention --version`.
+fn main() {
+    let commit = Command::new("git")
+    .args(["rev-parse", "--short", "HEAD"])
+    .output()
+    .ok()
+    .filter(|output| output.status.success())
+    .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    .unwrap_or_else(|| "unknown".to_owned());
+
+    // SOURCE_DATE_EPOCH keeps reproducible builds reproducible.
+    let built = env::var("SOURCE_DATE_EPOCH").ok()
+    .and_then(|epoch| epoch.parse().ok())
+    .and_then(|epoch| DateTime::<Utc>::from_timestamp(epoch, 0))
+    .unwrap_or_else(Utc::now);
+
+    println!("cargo:rustc-env=ATTENTION_GIT_COMMIT={}", commit);
+    println!("cargo:rustc-env=ATTENTION_BUILD_DATE={}", built.format("%Y-%m-%d"));
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
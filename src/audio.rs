@@ -0,0 +1,440 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::path::Path;
+
+use clap::ValueEnum;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::AttentionError;
+use crate::runner::{in_path, run_command, CommandRunner};
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioBackend {
+    // Re-query pactl only when `pactl subscribe` reports a sink-input change.
+    Subscribe,
+    #[value(name = "poll", alias = "pactl")]
+    #[serde(rename = "poll", alias = "pactl")]
+    Pactl,
+    Pipewire,
+    Auto
+}
+
+// What --match-regex is matched against on a stream unless --audio-match-prop
+// says otherwise.
+pub(crate) const DEFAULT_MATCH_PROPS: [&str; 3] = ["application.name", "media.name", "application.process.binary"];
+
+// What a stream says it's for in media.role, notification sounds and beeps
+// are "event". Anything but `any` only takes streams with that role.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, ValueEnum)]
+pub enum AudioRole {
+    Video,
+    Music,
+    Any
+}
+
+impl AudioRole {
+    fn allows(self, role: Option<&str>) -> bool {
+        match self {
+            AudioRole::Video => role == Some("video"),
+            AudioRole::Music => role == Some("music"),
+            AudioRole::Any => true
+        }
+    }
+}
+
+// --audio-match-prop and --audio-role, which streams count beyond the PIDs.
+#[derive(Clone)]
+pub struct StreamFilter {
+    pub props: Vec<String>,
+    pub role: AudioRole
+}
+
+impl Default for StreamFilter {
+    fn default() -> Self {
+        Self { props: DEFAULT_MATCH_PROPS.map(str::to_owned).to_vec(), role: AudioRole::Any }
+    }
+}
+
+pub struct SinkInput {
+    index: Option<u32>,
+    corked: bool,
+    properties: HashMap<String, String>
+}
+
+impl SinkInput {
+    fn property(&self, name: &str) -> Option<&str> {
+        self.properties.get(name).map(String::as_str)
+    }
+
+    fn pid(&self) -> Option<u32> {
+        self.property("application.process.id")?.parse().ok()
+    }
+
+    fn name_matches(&self, regex: &Regex, props: &[String]) -> bool {
+        props
+        .iter()
+        .filter_map(|name| self.property(name))
+        .any(|value| regex.is_match(value))
+    }
+
+    // Only whose stream it is and whether it's corked say anything, which
+    // sink it's routed to and what that sink adds to its properties don't.
+    // Streams going to a Bluetooth sink have no stream.is-live, for one.
+    fn is_playing(&self, role: AudioRole) -> bool {
+        !self.corked && role.allows(self.property("media.role"))
+    }
+}
+
+// Every stream starts at an unindented `Sink Input #<n>` header, its
+// `Properties:` are the indented `key = "value"` lines that follow. Only a
+// header starts a block, a media name can say "Sink Input #" too.
+pub(crate) fn parse_sink_inputs(stdout: &str) -> Vec<SinkInput> {
+    let mut sink_inputs: Vec<SinkInput> = Vec::new();
+    for line in stdout.lines() {
+        if let Some(index) = line.strip_prefix("Sink Input #") {
+            sink_inputs.push(SinkInput { index: index.trim().parse().ok(), corked: false, properties: HashMap::new() });
+            continue;
+        }
+        let Some(sink_input) = sink_inputs.last_mut() else {
+            continue;
+        };
+        let line = line.trim();
+        if let Some(corked) = line.strip_prefix("Corked:") {
+            sink_input.corked = corked.trim().eq_ignore_ascii_case("yes");
+        } else if let Some((key, value)) = line.split_once(" = ") {
+            sink_input.properties.insert(key.to_owned(), value.trim_matches('"').to_owned());
+        }
+    }
+    sink_inputs
+}
+
+// `pw-dump` lists every object on the graph, playback streams are the nodes
+// of class "Stream/Output/Audio" and they're only "running" while not corked.
+pub(crate) fn pipewire_stream_is_playing(object: &Value, pids: &HashSet<u32>, name: Option<&Regex>, filter: &StreamFilter) -> bool {
+    pipewire_stream_is_ours(object, pids, name, &filter.props)
+    && object["info"]["state"] == "running"
+    && filter.role.allows(object["info"]["props"]["media.role"].as_str())
+}
+
+fn pipewire_stream_is_ours(object: &Value, pids: &HashSet<u32>, name: Option<&Regex>, names: &[String]) -> bool {
+    let Some(info) = object.get("info") else {
+        return false;
+    };
+    let props = &info["props"];
+
+    let stream_pid = match &props["application.process.id"] {
+        Value::Number(number) => number.as_u64(),
+        Value::String(string) => string.parse().ok(),
+        _ => None
+    };
+
+    object["type"] == "PipeWire:Interface:Node"
+    && props["media.class"] == "Stream/Output/Audio"
+    && (stream_pid.is_some_and(|stream_pid| pids.iter().any(|&pid| pid as u64 == stream_pid))
+        || name.is_some_and(|regex| names
+        .iter()
+        .filter_map(|key| props[key.as_str()].as_str())
+        .any(|value| regex.is_match(value))))
+}
+
+// Picks pw-dump when PipeWire owns the session, its socket is there even when
+// pipewire-pulse is installed too. Otherwise `pactl subscribe` if there's a
+// pactl, and polling pactl as the last resort.
+pub(crate) fn detect_audio_backend() -> AudioBackend {
+    let pipewire_socket = env::var_os("XDG_RUNTIME_DIR")
+    .is_some_and(|dir| Path::new(&dir).join("pipewire-0").exists());
+
+    pick_audio_backend(pipewire_socket && in_path("pw-dump"), in_path("pactl"))
+}
+
+fn pick_audio_backend(pipewire: bool, pactl: bool) -> AudioBackend {
+    match (pipewire, pactl) {
+        (true, _) => AudioBackend::Pipewire,
+        (false, true) => AudioBackend::Subscribe,
+        (false, false) => AudioBackend::Pactl
+    }
+}
+
+// `pids` is the app's whole process tree, browsers and Electron apps play from
+// a child process rather than the one we launched. With `name` a stream also
+// counts when one of the filter's props, the application or media name by
+// default, matches it.
+pub(crate) fn is_playing_audio(runner: &dyn CommandRunner, backend: AudioBackend, pids: &HashSet<u32>, name: Option<&Regex>, filter: &StreamFilter) -> Result<bool, AttentionError> {
+    match backend {
+        AudioBackend::Pipewire => {
+            let output = run_command(runner, "pw-dump", &[])?;
+
+            let objects: Vec<Value> = serde_json::from_slice(&output.stdout)
+            .map_err(|err| AttentionError::UnexpectedOutput { cmd: "pw-dump".to_owned(), reason: err.to_string() })?;
+            Ok(objects.iter().any(|object| pipewire_stream_is_playing(object, pids, name, filter)))
+        }
+        AudioBackend::Subscribe | AudioBackend::Pactl | AudioBackend::Auto => {
+            let output = run_command(runner, "pactl", &["list", "sink-inputs"])?;
+
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            Ok(parse_sink_inputs(&stdout)
+            .iter()
+            .any(|sink_input| {
+                let ours = sink_input.pid().is_some_and(|pid| pids.contains(&pid))
+                || name.is_some_and(|regex| sink_input.name_matches(regex, &filter.props));
+                ours && sink_input.is_playing(filter.role)
+            }))
+        }
+    }
+}
+
+// Backs `attention debug`, one line per stream that counts as the app's
+// whether it's playing or not.
+pub(crate) fn stream_lines(runner: &dyn CommandRunner, backend: AudioBackend, pids: &HashSet<u32>, name: Option<&Regex>, props: &[String]) -> Result<Vec<String>, AttentionError> {
+    match backend {
+        AudioBackend::Pipewire => {
+            let output = run_command(runner, "pw-dump", &[])?;
+
+            let objects: Vec<Value> = serde_json::from_slice(&output.stdout)
+            .map_err(|err| AttentionError::UnexpectedOutput { cmd: "pw-dump".to_owned(), reason: err.to_string() })?;
+            Ok(objects
+            .iter()
+            .filter(|object| pipewire_stream_is_ours(object, pids, name, props))
+            .map(|object| format!("pw-dump: node {} ({}, {})", object["id"], object["info"]["props"]["application.name"].as_str().unwrap_or("?"), object["info"]["state"].as_str().unwrap_or("?")))
+            .collect())
+        }
+        AudioBackend::Subscribe | AudioBackend::Pactl | AudioBackend::Auto => {
+            let output = run_command(runner, "pactl", &["list", "sink-inputs"])?;
+
+            Ok(parse_sink_inputs(&String::from_utf8_lossy(&output.stdout))
+            .iter()
+            .filter(|sink_input| sink_input.pid().is_some_and(|pid| pids.contains(&pid))
+                || name.is_some_and(|regex| sink_input.name_matches(regex, props)))
+            .map(|sink_input| format!("pactl: Sink Input #{} ({}, corked: {}, media.role: {})",
+                sink_input.index.map_or("?".to_owned(), |index| index.to_string()),
+                sink_input.property("application.name").unwrap_or("?"),
+                if sink_input.corked { "yes" } else { "no" },
+                sink_input.property("media.role").unwrap_or("?")))
+            .collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::MockRunner;
+
+    fn filter() -> StreamFilter {
+        StreamFilter::default()
+    }
+
+    const PACTL: &str = "\
+Sink Input #71
+\tDriver: PipeWire
+\tCorked: no
+\tProperties:
+\t\tapplication.name = \"mpv\"
+\t\tapplication.process.id = \"9876\"
+\t\tstream.is-live = \"true\"
+";
+
+    const PACTL_STREAMS: &str = "\
+Sink Input #64
+\tDriver: PipeWire
+\tClient: 61
+\tCorked: no
+\tMute: no
+\tProperties:
+\t\tmedia.name = \"Playback\"
+\t\tapplication.name = \"Firefox\"
+\t\tapplication.process.id = \"2451\"
+\t\tapplication.process.binary = \"firefox\"
+\t\tstream.is-live = \"true\"
+Sink Input #71
+\tDriver: PipeWire
+\tClient: 70
+\tCorked: yes
+\tMute: no
+\tProperties:
+\t\tmedia.name = \"video.mkv - mpv\"
+\t\tapplication.name = \"mpv\"
+\t\tapplication.process.id = \"9876\"
+\t\tapplication.process.binary = \"mpv\"
+\t\tstream.is-live = \"true\"
+";
+
+    #[test]
+    fn pipewire_wins_over_pactl_when_it_runs_the_session() {
+        assert_eq!(pick_audio_backend(true, true), AudioBackend::Pipewire);
+        assert_eq!(pick_audio_backend(false, true), AudioBackend::Subscribe);
+        assert_eq!(pick_audio_backend(false, false), AudioBackend::Pactl);
+    }
+
+    #[test]
+    fn playing_audio_is_detected_from_pactl() {
+        let runner = MockRunner::new().with("pactl", PACTL);
+        assert!(is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::from([9876]), None, &filter()).unwrap());
+        assert!(!is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::from([1111]), None, &filter()).unwrap());
+    }
+
+    #[test]
+    fn corked_stream_is_not_playing() {
+        let runner = MockRunner::new().with("pactl", &PACTL.replace("Corked: no", "Corked: yes"));
+        assert!(!is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::from([9876]), None, &filter()).unwrap());
+    }
+
+    const PW_DUMP: &str = r#"[
+        {"id": 0, "type": "PipeWire:Interface:Core", "info": {"props": {}}},
+        {"id": 56, "type": "PipeWire:Interface:Node", "info": {"state": "suspended", "props": {
+            "media.class": "Audio/Sink", "node.name": "alsa_output.pci-0000_00_1f.3.analog-stereo"}}},
+        {"id": 83, "type": "PipeWire:Interface:Node", "info": {"state": "running", "props": {
+            "media.class": "Stream/Output/Audio", "application.name": "mpv", "application.process.id": 9876}}},
+        {"id": 91, "type": "PipeWire:Interface:Node", "info": {"state": "idle", "props": {
+            "media.class": "Stream/Output/Audio", "application.name": "Firefox", "application.process.id": 2451}}},
+        {"id": 95, "type": "PipeWire:Interface:Node", "info": {"state": "running", "props": {
+            "media.class": "Stream/Input/Audio", "application.name": "OBS", "application.process.id": 3003}}}
+    ]"#;
+
+    #[test]
+    fn pipewire_streams_are_read_from_pw_dump() {
+        let runner = MockRunner::new().with("pw-dump", PW_DUMP);
+        assert!(is_playing_audio(&runner, AudioBackend::Pipewire, &HashSet::from([9876]), None, &filter()).unwrap());
+        assert!(!is_playing_audio(&runner, AudioBackend::Pipewire, &HashSet::from([2451]), None, &filter()).unwrap());
+        assert!(!is_playing_audio(&runner, AudioBackend::Pipewire, &HashSet::from([3003]), None, &filter()).unwrap());
+    }
+
+    #[test]
+    fn sink_inputs_are_split_into_blocks() {
+        let sink_inputs = parse_sink_inputs(PACTL_STREAMS);
+        assert_eq!(sink_inputs.len(), 2);
+        assert_eq!(sink_inputs[0].pid(), Some(2451));
+        assert!(!sink_inputs[0].corked);
+        assert_eq!(sink_inputs[1].property("application.name"), Some("mpv"));
+        assert!(sink_inputs[1].corked);
+    }
+
+    #[test]
+    fn only_the_stream_of_our_pid_counts() {
+        let runner = MockRunner::new().with("pactl", PACTL_STREAMS);
+        assert!(is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::from([2451]), None, &filter()).unwrap());
+        assert!(!is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::from([9876]), None, &filter()).unwrap());
+        assert!(!is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::from([245]), None, &filter()).unwrap());
+    }
+
+    #[test]
+    fn streams_of_child_processes_count() {
+        let runner = MockRunner::new().with("pactl", PACTL_STREAMS);
+        assert!(is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::from([1000, 2451]), None, &filter()).unwrap());
+        assert!(!is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::from([1000, 1001]), None, &filter()).unwrap());
+    }
+
+    // The apps and `Corked: no` are both there, just never in the same block.
+    const PACTL_SCATTERED: &str = "\
+Sink Input #64
+\tCorked: no
+\tProperties:
+\t\tmedia.name = \"Sink Input #71 Corked: no\"
+\t\tapplication.name = \"speech-dispatcher\"
+\t\tapplication.process.id = \"1500\"
+Sink Input #71
+\tCorked: yes
+\tProperties:
+\t\tapplication.name = \"mpv\"
+\t\tapplication.process.id = \"9876\"
+\t\tstream.is-live = \"false\"
+Sink Input #80
+\tCorked: yes
+\tProperties:
+\t\tapplication.name = \"Spotify\"
+\t\tapplication.process.id = \"3003\"
+\t\tstream.is-live = \"true\"
+";
+
+    #[test]
+    fn scattered_matches_across_streams_dont_count() {
+        let runner = MockRunner::new().with("pactl", PACTL_SCATTERED);
+        assert_eq!(parse_sink_inputs(PACTL_SCATTERED).len(), 3);
+        assert!(!is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::from([9876, 3003]), None, &filter()).unwrap());
+        let regex = Regex::new("mpv|Spotify").unwrap();
+        assert!(!is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::new(), Some(&regex), &filter()).unwrap());
+    }
+
+    // PulseAudio with a pair of headphones, the sink's latency and format
+    // come along and stream.is-live doesn't.
+    const PACTL_BLUETOOTH: &str = "\
+Sink Input #112
+\tDriver: protocol-native.c
+\tOwner Module: 10
+\tClient: 95
+\tSink: 3
+\tSample Specification: s16le 2ch 44100Hz
+\tChannel Map: front-left,front-right
+\tFormat: pcm, format.sample_format = \"\\\"s16le\\\"\"  format.rate = \"44100\"  format.channels = \"2\"
+\tCorked: no
+\tMute: no
+\tVolume: front-left: 42597 /  65% / -11.23 dB,   front-right: 42597 /  65% / -11.23 dB
+\tBuffer Latency: 128820 usec
+\tSink Latency: 191243 usec
+\tResample method: (null)
+\tProperties:
+\t\tmedia.name = \"Playback\"
+\t\tapplication.name = \"Firefox\"
+\t\tnative-protocol.peer = \"UNIX socket client\"
+\t\tapplication.process.id = \"2451\"
+\t\tapplication.process.binary = \"firefox\"
+\t\tmodule-stream-restore.id = \"sink-input-by-application-name:Firefox\"
+";
+
+    #[test]
+    fn streams_on_a_bluetooth_sink_count_too() {
+        let runner = MockRunner::new().with("pactl", PACTL_BLUETOOTH);
+        assert!(is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::from([2451]), None, &filter()).unwrap());
+        let runner = MockRunner::new().with("pactl", &PACTL_BLUETOOTH.replace("Corked: no", "Corked: yes"));
+        assert!(!is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::from([2451]), None, &filter()).unwrap());
+    }
+
+    #[test]
+    fn streams_can_be_matched_by_name() {
+        let runner = MockRunner::new().with("pactl", PACTL_STREAMS);
+        let regex = Regex::new("YouTube|Firefox").unwrap();
+        assert!(is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::new(), Some(&regex), &filter()).unwrap());
+        let regex = Regex::new("Spotify").unwrap();
+        assert!(!is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::new(), Some(&regex), &filter()).unwrap());
+    }
+
+    const PACTL_ROLES: &str = "\
+Sink Input #80
+\tCorked: no
+\tProperties:
+\t\tmedia.role = \"event\"
+\t\tapplication.process.id = \"2451\"
+\t\tstream.is-live = \"true\"
+Sink Input #81
+\tCorked: no
+\tProperties:
+\t\tmedia.role = \"video\"
+\t\tapplication.process.id = \"9876\"
+\t\tstream.is-live = \"true\"
+";
+
+    #[test]
+    fn only_streams_with_the_chosen_role_count() {
+        let runner = MockRunner::new().with("pactl", PACTL_ROLES);
+        let video = StreamFilter { role: AudioRole::Video, ..filter() };
+        let music = StreamFilter { role: AudioRole::Music, ..filter() };
+        assert!(is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::from([2451]), None, &filter()).unwrap());
+        assert!(!is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::from([2451]), None, &video).unwrap());
+        assert!(is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::from([9876]), None, &video).unwrap());
+        assert!(!is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::from([9876]), None, &music).unwrap());
+    }
+
+    #[test]
+    fn names_are_only_matched_against_chosen_props() {
+        let runner = MockRunner::new().with("pactl", PACTL_STREAMS);
+        let regex = Regex::new("^firefox$").unwrap();
+        assert!(is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::new(), Some(&regex), &filter()).unwrap());
+        let name_only = StreamFilter { props: vec!["application.name".to_owned()], ..filter() };
+        assert!(!is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::new(), Some(&regex), &name_only).unwrap());
+        let regex = Regex::new("true").unwrap();
+        assert!(!is_playing_audio(&runner, AudioBackend::Pactl, &HashSet::new(), Some(&regex), &filter()).unwrap());
+    }
+}
@@ -0,0 +1,204 @@
+use core::time;
+use std::iter::Peekable;
+use std::str::Chars;
+
+// What `--condition` can ask about, each is looked up every loop but only
+// when the expression mentions it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signal {
+    Audio,
+    Fullscreen,
+    Focused,
+    Idle
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    Is(Signal),
+    IdleBelow(time::Duration),
+    IdleAbove(time::Duration),
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>)
+}
+
+#[derive(Default)]
+pub(crate) struct Signals {
+    pub audio: bool,
+    pub fullscreen: bool,
+    pub focused: bool,
+    pub idle: time::Duration
+}
+
+impl Condition {
+    // What --track-audio and --track-fullscreen stand for.
+    pub fn from_flags(audio: bool, fullscreen: bool) -> Option<Self> {
+        match (audio, fullscreen) {
+            (true, true) => Some(Condition::Or(Box::new(Condition::Is(Signal::Audio)), Box::new(Condition::Is(Signal::Fullscreen)))),
+            (true, false) => Some(Condition::Is(Signal::Audio)),
+            (false, true) => Some(Condition::Is(Signal::Fullscreen)),
+            (false, false) => None
+        }
+    }
+
+    pub fn uses(&self, signal: Signal) -> bool {
+        match self {
+            Condition::Is(used) => *used == signal,
+            Condition::IdleBelow(_) | Condition::IdleAbove(_) => signal == Signal::Idle,
+            Condition::Not(inner) => inner.uses(signal),
+            Condition::And(left, right) | Condition::Or(left, right) => left.uses(signal) || right.uses(signal)
+        }
+    }
+
+    pub(crate) fn eval(&self, signals: &Signals) -> bool {
+        match self {
+            Condition::Is(Signal::Audio) => signals.audio,
+            Condition::Is(Signal::Fullscreen) => signals.fullscreen,
+            Condition::Is(Signal::Focused) => signals.focused,
+            // Bare `idle` is whatever xprintidle counts as any idleness at all.
+            Condition::Is(Signal::Idle) => !signals.idle.is_zero(),
+            Condition::IdleBelow(limit) => signals.idle < *limit,
+            Condition::IdleAbove(limit) => signals.idle > *limit,
+            Condition::Not(inner) => !inner.eval(signals),
+            Condition::And(left, right) => left.eval(signals) && right.eval(signals),
+            Condition::Or(left, right) => left.eval(signals) || right.eval(signals)
+        }
+    }
+}
+
+// `||` binds looser than `&&`, which binds looser than `!`, parentheses
+// group. `idle` compares against a duration in seconds or minutes, `idle<60s`
+// or `idle>5m`.
+pub(crate) fn parse_condition(value: &str) -> Result<Condition, String> {
+    let mut parser = Parser { chars: value.chars().peekable() };
+    let condition = parser.or()?;
+    parser.skip_spaces();
+    match parser.chars.next() {
+        None => Ok(condition),
+        Some(c) => Err(format!("unexpected {:?}", c))
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>
+}
+
+impl Parser<'_> {
+    fn skip_spaces(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    // Consumes `op` when it's next, a lone `&` or `|` is a mistake.
+    fn eat(&mut self, op: &str) -> Result<bool, String> {
+        self.skip_spaces();
+        let mut rest = op.chars();
+        if self.chars.peek() != rest.next().as_ref() {
+            return Ok(false);
+        }
+        self.chars.next();
+        for expected in rest {
+            if self.chars.next() != Some(expected) {
+                return Err(format!("expected {}", op));
+            }
+        }
+        Ok(true)
+    }
+
+    fn or(&mut self) -> Result<Condition, String> {
+        let mut condition = self.and()?;
+        while self.eat("||")? {
+            condition = Condition::Or(Box::new(condition), Box::new(self.and()?));
+        }
+        Ok(condition)
+    }
+
+    fn and(&mut self) -> Result<Condition, String> {
+        let mut condition = self.unary()?;
+        while self.eat("&&")? {
+            condition = Condition::And(Box::new(condition), Box::new(self.unary()?));
+        }
+        Ok(condition)
+    }
+
+    fn unary(&mut self) -> Result<Condition, String> {
+        if self.eat("!")? {
+            return Ok(Condition::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("(")? {
+            let condition = self.or()?;
+            if !self.eat(")")? {
+                return Err("expected )".to_owned());
+            }
+            return Ok(condition);
+        }
+        self.signal()
+    }
+
+    fn word(&mut self, accept: fn(&char) -> bool) -> String {
+        self.skip_spaces();
+        let mut word = String::new();
+        while let Some(c) = self.chars.next_if(accept) {
+            word.push(c);
+        }
+        word
+    }
+
+    fn signal(&mut self) -> Result<Condition, String> {
+        match self.word(char::is_ascii_alphabetic).as_str() {
+            "audio" => Ok(Condition::Is(Signal::Audio)),
+            "fullscreen" => Ok(Condition::Is(Signal::Fullscreen)),
+            "focused" => Ok(Condition::Is(Signal::Focused)),
+            "idle" if self.eat("<")? => Ok(Condition::IdleBelow(self.duration()?)),
+            "idle" if self.eat(">")? => Ok(Condition::IdleAbove(self.duration()?)),
+            "idle" => Ok(Condition::Is(Signal::Idle)),
+            "" => Err("expected audio, fullscreen, focused or idle".to_owned()),
+            word => Err(format!("unknown signal {}, expected audio, fullscreen, focused or idle", word))
+        }
+    }
+
+    fn duration(&mut self) -> Result<time::Duration, String> {
+        let amount: u64 = self.word(char::is_ascii_digit).parse().map_err(|_| "expected a duration like 60s".to_owned())?;
+        match self.chars.next_if(|c| *c == 's' || *c == 'm') {
+            Some('m') => amount.checked_mul(60).map(time::Duration::from_secs).ok_or_else(|| format!("{}m is too long a duration", amount)),
+            _ => Ok(time::Duration::from_secs(amount))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let condition = parse_condition("fullscreen && audio || idle<60s").unwrap();
+        assert_eq!(condition, Condition::Or(
+            Box::new(Condition::And(Box::new(Condition::Is(Signal::Fullscreen)), Box::new(Condition::Is(Signal::Audio)))),
+            Box::new(Condition::IdleBelow(time::Duration::from_secs(60)))
+        ));
+        assert!(condition.uses(Signal::Idle) && !condition.uses(Signal::Focused));
+
+        let watching = Signals { fullscreen: true, audio: true, idle: time::Duration::from_secs(600), ..Signals::default() };
+        assert!(condition.eval(&watching));
+        assert!(!condition.eval(&Signals { audio: false, ..watching }));
+    }
+
+    #[test]
+    fn parentheses_and_negation_group() {
+        let condition = parse_condition("!(audio || focused) && idle > 5m").unwrap();
+        let signals = Signals { idle: time::Duration::from_secs(301), ..Signals::default() };
+        assert!(condition.eval(&signals));
+        assert!(!condition.eval(&Signals { focused: true, ..signals }));
+    }
+
+    #[test]
+    fn mistakes_are_pointed_out() {
+        assert!(parse_condition("audio & fullscreen").is_err());
+        assert!(parse_condition("audio ||").is_err());
+        assert!(parse_condition("(audio").is_err());
+        assert!(parse_condition("music").unwrap_err().contains("unknown signal music"));
+        assert!(parse_condition("idle<soon").is_err());
+        assert!(parse_condition("idle>999999999999999999m").unwrap_err().contains("too long"));
+        assert!(parse_condition("audio fullscreen").is_err());
+    }
+}
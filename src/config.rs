@@ -0,0 +1,248 @@
+use core::time;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use serde::Deserialize;
+
+use crate::audio::AudioBackend;
+use crate::error::AttentionError;
+use crate::inhibit::{InhibitMethod, Urgency};
+use crate::options::Options;
+
+// Defaults read from config.toml, anything set here loses to a flag given on
+// the command line but wins over the built-in default. `[profiles.<name>]`
+// tables take the same keys and fall back to the top level for the rest.
+#[derive(Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub app: Option<String>,
+    pub args: Option<Vec<String>>,
+    pub track_audio: Option<bool>,
+    pub track_fullscreen: Option<bool>,
+    pub interval: Option<u64>,
+    pub startup_interval: Option<u64>,
+    pub window_timeout: Option<u64>,
+    pub grace: Option<u64>,
+    pub audio_backend: Option<AudioBackend>,
+    pub inhibit_method: Option<InhibitMethod>,
+    pub reason: Option<String>,
+    pub match_class: Option<String>,
+    pub match_regex: Option<bool>,
+    pub no_notify: Option<bool>,
+    pub notify_on_text: Option<String>,
+    pub notify_off_text: Option<String>,
+    pub notify_icon: Option<String>,
+    pub notify_urgency: Option<Urgency>,
+    pub profiles: HashMap<String, Config>
+}
+
+// $XDG_CONFIG_HOME, ~/.config when that's unset.
+pub(crate) fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+    .filter(|dir| !dir.is_empty())
+    .map(PathBuf::from)
+    .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        Some(config_dir()?.join("attention").join("config.toml"))
+    }
+
+    // No config file is fine, a broken one isn't.
+    pub fn load() -> Result<Self, AttentionError> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(contents) => Self::parse(&contents).map_err(|reason| AttentionError::Config { path, reason }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(AttentionError::Config { path, reason: err.to_string() })
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        toml::from_str(contents).map_err(|err| err.message().to_owned())
+    }
+
+    pub fn profile(&self, name: &str) -> Result<Self, AttentionError> {
+        let profile = self.profiles.get(name)
+        .ok_or_else(|| AttentionError::UnknownProfile { name: name.to_owned(), known: self.profiles.keys().cloned().collect() })?;
+
+        Ok(Self {
+            app: profile.app.clone().or_else(|| self.app.clone()),
+            args: profile.args.clone().or_else(|| self.args.clone()),
+            track_audio: profile.track_audio.or(self.track_audio),
+            track_fullscreen: profile.track_fullscreen.or(self.track_fullscreen),
+            interval: profile.interval.or(self.interval),
+            startup_interval: profile.startup_interval.or(self.startup_interval),
+            window_timeout: profile.window_timeout.or(self.window_timeout),
+            grace: profile.grace.or(self.grace),
+            audio_backend: profile.audio_backend.or(self.audio_backend),
+            inhibit_method: profile.inhibit_method.or(self.inhibit_method),
+            reason: profile.reason.clone().or_else(|| self.reason.clone()),
+            match_class: profile.match_class.clone().or_else(|| self.match_class.clone()),
+            match_regex: profile.match_regex.or(self.match_regex),
+            no_notify: profile.no_notify.or(self.no_notify),
+            notify_on_text: profile.notify_on_text.clone().or_else(|| self.notify_on_text.clone()),
+            notify_off_text: profile.notify_off_text.clone().or_else(|| self.notify_off_text.clone()),
+            notify_icon: profile.notify_icon.clone().or_else(|| self.notify_icon.clone()),
+            notify_urgency: profile.notify_urgency.or(self.notify_urgency),
+            profiles: HashMap::new()
+        })
+    }
+
+    // Picks the --profile one if there is one, then fills in `options`.
+    pub fn apply_to(self, options: &mut Options, matches: &ArgMatches) -> Result<(), AttentionError> {
+        let config = match &options.profile {
+            Some(name) => self.profile(name)?,
+            None => self
+        };
+        config.apply(options, matches);
+        Ok(())
+    }
+
+    // `matches` tells a value typed on the command line apart from a clap
+    // default, only the latter gets replaced.
+    fn apply(self, options: &mut Options, matches: &ArgMatches) {
+        let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+
+        // The app and its arguments only come as a pair.
+        if options.app_name.is_none() && options.attach.is_none() && options.attach_title.is_none() {
+            options.app_name = self.app;
+            options.app_args = self.args.unwrap_or_default();
+        }
+        options.track_audio |= self.track_audio.unwrap_or(false);
+        options.track_fullscreen |= self.track_fullscreen.unwrap_or(false);
+
+        if let Some(interval) = self.interval.filter(|_| unset("interval")) {
+            options.interval = time::Duration::from_millis(interval);
+        }
+        if let Some(interval) = self.startup_interval.filter(|_| unset("startup_interval")) {
+            options.startup_interval = time::Duration::from_millis(interval);
+        }
+        if let Some(timeout) = self.window_timeout.filter(|_| unset("window_timeout")) {
+            options.window_timeout = time::Duration::from_secs(timeout);
+        }
+        if let Some(grace) = self.grace.filter(|_| unset("grace")) {
+            options.grace = time::Duration::from_secs(grace);
+        }
+        if let Some(backend) = self.audio_backend.filter(|_| unset("audio_backend")) {
+            options.audio_backend = backend;
+        }
+        if let Some(method) = self.inhibit_method.filter(|_| unset("inhibit_method")) {
+            options.inhibit_method = method;
+        }
+        // The match mode from the config only applies where it can't clash
+        // with one picked on the command line.
+        if let Some(class) = self.match_class.filter(|_| options.attach_title.is_none() && !options.match_regex && unset("match_class")) {
+            options.match_class = Some(class);
+        }
+        if let Some(match_regex) = self.match_regex.filter(|_| options.attach.is_none() && options.attach_title.is_none() && options.match_class.is_none() && unset("match_regex")) {
+            options.match_regex = match_regex;
+        }
+        if let Some(no_notify) = self.no_notify.filter(|_| unset("no_notify")) {
+            options.no_notify = no_notify;
+        }
+        if let Some(text) = self.reason.filter(|_| unset("reason")) {
+            options.reason = text;
+        }
+        if let Some(text) = self.notify_on_text.filter(|_| unset("notify_on_text")) {
+            options.notify_on_text = text;
+        }
+        if let Some(text) = self.notify_off_text.filter(|_| unset("notify_off_text")) {
+            options.notify_off_text = text;
+        }
+        if let Some(icon) = self.notify_icon.filter(|_| unset("notify_icon")) {
+            options.notify_icon = Some(icon);
+        }
+        if let Some(urgency) = self.notify_urgency.filter(|_| unset("notify_urgency")) {
+            options.notify_urgency = Some(urgency);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, FromArgMatches};
+
+    use super::*;
+
+    fn options_with(config: &str, args: &[&str]) -> Options {
+        let matches = Options::command().try_get_matches_from(args).unwrap();
+        let mut options = Options::from_arg_matches(&matches).unwrap();
+        Config::parse(config).unwrap().apply_to(&mut options, &matches).unwrap();
+        options
+    }
+
+    const CONFIG: &str = r#"
+interval = 500
+grace = 5
+inhibit-method = "dbus"
+notify-on-text = "{app} is on"
+reason = "{app} is playing"
+"#;
+
+    #[test]
+    fn config_fills_in_what_the_command_line_left_out() {
+        let options = options_with(CONFIG, &["attention", "--track-audio", "mpv"]);
+        assert_eq!(options.interval, time::Duration::from_millis(500));
+        assert_eq!(options.grace, time::Duration::from_secs(5));
+        assert!(options.inhibit_method == InhibitMethod::Dbus);
+        assert_eq!(options.notify_on_text, "{app} is on");
+        assert_eq!(options.reason, "{app} is playing");
+        assert_eq!(options.window_timeout, time::Duration::from_secs(30));
+    }
+
+    #[test]
+    fn command_line_beats_the_config() {
+        let options = options_with(CONFIG, &["attention", "--track-audio", "--interval", "2000", "--inhibit-method", "xset", "mpv"]);
+        assert_eq!(options.interval, time::Duration::from_millis(2000));
+        assert!(options.inhibit_method == InhibitMethod::Xset);
+
+        let options = options_with("match-regex = false", &["attention", "--track-audio", "--match-regex", "YouTube|Spotify"]);
+        assert!(options.match_regex);
+    }
+
+    const PROFILES: &str = r#"
+grace = 5
+interval = 500
+
+[profiles.youtube]
+app = "firefox"
+args = ["https://youtube.com"]
+track-audio = true
+grace = 30
+
+[profiles.game]
+track-fullscreen = true
+"#;
+
+    #[test]
+    fn profiles_inherit_from_the_top_level() {
+        let matches = Options::command().try_get_matches_from(["attention", "--profile", "youtube"]).unwrap();
+        let mut options = Options::from_arg_matches(&matches).unwrap();
+        Config::parse(PROFILES).unwrap().apply_to(&mut options, &matches).unwrap();
+        assert_eq!(options.app_name.as_deref(), Some("firefox"));
+        assert_eq!(options.app_args, vec!["https://youtube.com"]);
+        assert!(options.track_audio && !options.track_fullscreen);
+        assert_eq!(options.grace, time::Duration::from_secs(30));
+        assert_eq!(options.interval, time::Duration::from_millis(500));
+    }
+
+    #[test]
+    fn missing_profiles_are_an_error() {
+        let config = Config::parse(PROFILES).unwrap();
+        assert!(matches!(config.profile("mpv"), Err(AttentionError::UnknownProfile { .. })));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(Config::parse("intervall = 500").is_err());
+    }
+}
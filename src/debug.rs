@@ -0,0 +1,164 @@
+use core::time;
+use std::thread::sleep;
+use std::time::Instant;
+
+use log::{debug, log_enabled, Level};
+
+use crate::audio::{is_playing_audio, stream_lines, AudioBackend, StreamFilter};
+use crate::error::AttentionError;
+use crate::procfs::process_tree;
+use crate::runner::CommandRunner;
+use crate::session::SessionType;
+use crate::state::State;
+use crate::window::{active_window_id, find_matching_window, is_window_focused, is_window_fullscreen, window_lines, WindowMatch};
+
+// Long enough for a window that's just opening, short enough not to look hung.
+const DEBUG_TIMEOUT: time::Duration = time::Duration::from_secs(2);
+
+fn answer(result: Result<bool, AttentionError>) -> String {
+    match result {
+        Ok(true) => "yes".to_owned(),
+        Ok(false) => "no".to_owned(),
+        Err(err) => format!("failed, {}", err)
+    }
+}
+
+// Backs `attention debug`, runs every check once against the app's window and
+// reports what each one saw. A check failing is part of the report, only not
+// finding the window at all ends it early.
+pub(crate) fn report(runner: &dyn CommandRunner, session: SessionType, backend: AudioBackend, app_name: &str, timeout: time::Duration) -> (Vec<String>, Result<(), AttentionError>) {
+    let mut lines = vec![
+        format!("session: {}", match session { SessionType::X11 => "x11", SessionType::Sway => "sway", SessionType::Hyprland => "hyprland" }),
+        format!("audio backend: {:?}", backend).to_lowercase()
+    ];
+
+    let matching = WindowMatch::Title(app_name.to_owned());
+    let started = Instant::now();
+    let found = loop {
+        match find_matching_window(runner, session, &matching) {
+            Ok(None) if started.elapsed() < timeout => sleep(time::Duration::from_millis(200)),
+            found => break found
+        }
+    };
+    let (id, pid) = match found {
+        Ok(Some(window)) => window,
+        Ok(None) => {
+            lines.push(format!("window: no title contains {:?}", app_name));
+            return (lines, Err(AttentionError::NoMatchingWindow { what: format!("the title {:?}", app_name) }));
+        }
+        Err(err) => {
+            lines.push(format!("window: failed, {}", err));
+            return (lines, Err(err));
+        }
+    };
+
+    lines.push(format!("window: {} (PID {})", id, pid));
+    match window_lines(runner, session, &id) {
+        Ok(matched) => lines.extend(matched.into_iter().map(|line| format!("  {}", line))),
+        Err(err) => lines.push(format!("  failed, {}", err))
+    }
+    lines.push(format!("fullscreen: {}", answer(is_window_fullscreen(runner, session, &id))));
+    lines.push(format!("focused: {}", answer(is_window_focused(runner, session, &id))));
+
+    let pids = process_tree(pid);
+    lines.push(format!("playing audio: {}", answer(is_playing_audio(runner, backend, &pids, None, &StreamFilter::default()))));
+    match stream_lines(runner, backend, &pids, None, &[]) {
+        Ok(matched) if matched.is_empty() => lines.push("  no streams of its process tree".to_owned()),
+        Ok(matched) => lines.extend(matched.into_iter().map(|line| format!("  {}", line))),
+        Err(err) => lines.push(format!("  failed, {}", err))
+    }
+    (lines, Ok(()))
+}
+
+pub(crate) fn debug(runner: &dyn CommandRunner, session: SessionType, backend: AudioBackend, app_name: &str) -> Result<(), AttentionError> {
+    let (lines, result) = report(runner, session, backend, app_name, DEBUG_TIMEOUT);
+    for line in lines {
+        println!("{}", line);
+    }
+    result
+}
+
+// --debug-dump is due every so many seconds, and only when -v would show it.
+pub(crate) fn dump_due(state: &mut State) -> bool {
+    let Some(every) = state.debug_dump else {
+        return false;
+    };
+    if !log_enabled!(Level::Debug) || state.dumped_at.is_some_and(|dumped_at| dumped_at.elapsed() < every) {
+        return false;
+    }
+    state.dumped_at = Some(Instant::now());
+    true
+}
+
+// What the checks say about the tracked window right now, asked afresh so a
+// log shows when they and the trackers part ways.
+pub(crate) fn dump_line(runner: &dyn CommandRunner, state: &State) -> Option<String> {
+    let window = state.window.as_ref()?;
+    let fullscreen = answer(is_window_fullscreen(runner, state.session, &window.id));
+    let audio = answer(is_playing_audio(runner, state.audio_backend, &process_tree(window.pid), window.stream_regex(), &state.stream_filter));
+    let active = match state.session {
+        SessionType::X11 => format!("active window {}", active_window_id(runner).unwrap_or_else(|err| format!("failed, {}", err))),
+        _ => format!("focused {}", answer(is_window_focused(runner, state.session, &window.id)))
+    };
+    Some(format!("{}: window {} (PID {}), fullscreen {}, audio {}, {}", state.app_name, window.id, window.pid, fullscreen, audio, active))
+}
+
+pub(crate) fn dump_window(runner: &dyn CommandRunner, state: &State) {
+    if let Some(line) = dump_line(runner, state) {
+        debug!("{}", line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::MockRunner;
+    use crate::window::mpv_window;
+
+    const WMCTRL: &str = "0x03a00006  0 9876   host mpv - video.mkv\n";
+
+    const PACTL: &str = "\
+Sink Input #71
+\tCorked: yes
+\tProperties:
+\t\tapplication.name = \"mpv\"
+\t\tapplication.process.id = \"9876\"
+\t\tstream.is-live = \"true\"
+";
+
+    #[test]
+    fn the_report_shows_what_every_check_saw() {
+        let runner = MockRunner::new()
+        .with("wmctrl", WMCTRL)
+        .with("xprop", "_NET_WM_STATE(ATOM) = _NET_WM_STATE_FULLSCREEN\n")
+        .with("pactl", PACTL);
+        let (lines, result) = report(&runner, SessionType::X11, AudioBackend::Pactl, "mpv", time::Duration::ZERO);
+        assert!(result.is_ok());
+        assert_eq!(lines[2], "window: 0x03a00006 (PID 9876)");
+        assert_eq!(lines[3], "  wmctrl: 0x03a00006  0 9876   host mpv - video.mkv");
+        assert_eq!(lines[4], "fullscreen: yes");
+        assert!(lines[5].starts_with("focused: failed"));
+        assert_eq!(lines[6], "playing audio: no");
+        assert_eq!(lines[7], "  pactl: Sink Input #71 (mpv, corked: yes, media.role: ?)");
+    }
+
+    #[test]
+    fn a_missing_window_ends_the_report() {
+        let runner = MockRunner::new().with("wmctrl", WMCTRL);
+        let (lines, result) = report(&runner, SessionType::X11, AudioBackend::Pactl, "vlc", time::Duration::ZERO);
+        assert_eq!(result.unwrap_err().to_string(), "No window matching the title \"vlc\"");
+        assert_eq!(lines.last().unwrap(), "window: no title contains \"vlc\"");
+    }
+    #[test]
+    fn a_dump_is_one_line_about_the_tracked_window() {
+        let runner = MockRunner::new()
+        .with("xprop", "_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00006\n")
+        .with("pactl", PACTL);
+        let mut state = State::new(SessionType::X11);
+        assert!(dump_line(&runner, &state).is_none());
+
+        state.app_name = "mpv".to_owned();
+        state.window = Some(mpv_window());
+        assert_eq!(dump_line(&runner, &state).unwrap(), "mpv: window 0x03a00006 (PID 9876), fullscreen no, audio no, active window 0x3a00006");
+    }
+}
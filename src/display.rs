@@ -0,0 +1,112 @@
+use core::time;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+use log::{debug, warn};
+
+use crate::error::AttentionError;
+use crate::event::{report, Event};
+use crate::inhibit::turn_on_screen_blanking;
+use crate::runner::{run_command, CommandRunner};
+use crate::saved::save;
+use crate::service::watchdog;
+use crate::state::{lock, FullscreenState, ScreenBlankingState, State, TrackAudioState};
+
+// wmctrl says "Cannot open display", xprop and xset "unable to open display",
+// anything else is left to fail as before.
+pub(crate) fn display_gone(err: &AttentionError) -> bool {
+    match err {
+        AttentionError::CommandFailed { stderr, .. } => stderr.to_lowercase().contains("open display"),
+        _ => false
+    }
+}
+
+// The server DPMS was turned off on took that with it, a new one starts with
+// blanking on. The trackers start over so they turn it off again once
+// they've had a look at the new one.
+pub(crate) fn display_lost(runner: &dyn CommandRunner, state: &mut State) {
+    report(state.json, Event::DisplayLost);
+    if let Err(err) = turn_on_screen_blanking(runner, state) {
+        debug!("{}", err);
+        state.last_screen_blanking_state = ScreenBlankingState::On;
+        state.metrics.released();
+        state.inhibit_reasons.clear();
+        save(state);
+    }
+    state.last_fullscreen_state = FullscreenState::NotFullscreen;
+    state.fullscreen_pending = 0;
+    state.last_track_audio_state = TrackAudioState::Off;
+    state.mpris_playing = false;
+    state.silence_since = None;
+}
+
+// Asks xset until the display answers again, waiting a second and doubling
+// up to 30s in between. The state is only locked to report, the socket
+// keeps answering meanwhile.
+pub(crate) fn wait_for_display(runner: &dyn CommandRunner, timeout: time::Duration, state: &Mutex<State>) -> Result<(), AttentionError> {
+    let started = Instant::now();
+    let mut backoff = time::Duration::from_secs(1);
+    loop {
+        watchdog();
+        match run_command(runner, "xset", &["q"]) {
+            Ok(_) => {
+                let state = lock(state);
+                report(state.json, Event::DisplayBack { app: &state.app_name });
+                return Ok(());
+            }
+            Err(err) if started.elapsed() >= timeout => {
+                debug!("{}", err);
+                return Err(AttentionError::DisplayTimeout { timeout });
+            }
+            Err(err) => {
+                warn!("{}, trying again in {}s..", err, backoff.as_secs());
+                thread::sleep(backoff.min(timeout.saturating_sub(started.elapsed())));
+                backoff = (backoff * 2).min(time::Duration::from_secs(30));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::MockRunner;
+    use crate::session::SessionType;
+    use crate::state::InhibitReason;
+
+    #[test]
+    fn only_a_missing_display_counts_as_gone() {
+        let gone = |stderr: &str| display_gone(&AttentionError::CommandFailed { cmd: "wmctrl".to_owned(), stderr: stderr.to_owned() });
+        assert!(gone("Cannot open display."));
+        assert!(gone("xprop:  unable to open display ':0'"));
+        assert!(!gone("X Error of failed request:  BadWindow"));
+        assert!(!display_gone(&AttentionError::CommandTimeout { cmd: "xset".to_owned() }));
+    }
+
+    #[test]
+    fn a_lost_display_leaves_nothing_inhibited() {
+        let runner = MockRunner::new().failing("xset", "xset:  unable to open display \":0\"");
+        let mut state = State::new(SessionType::X11);
+        state.last_screen_blanking_state = ScreenBlankingState::Off;
+        state.last_track_audio_state = TrackAudioState::On;
+        state.inhibit_reasons.insert(InhibitReason::Audio);
+
+        display_lost(&runner, &mut state);
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::On);
+        assert!(state.last_track_audio_state == TrackAudioState::Off);
+        assert!(state.inhibit_reasons.is_empty());
+    }
+
+    #[test]
+    fn waiting_for_the_display_gives_up_after_the_timeout() {
+        let state = Mutex::new(State::new(SessionType::X11));
+        let runner = MockRunner::new().with("xset", "");
+        assert!(wait_for_display(&runner, time::Duration::ZERO, &state).is_ok());
+
+        let runner = MockRunner::new().failing("xset", "xset:  unable to open display \":0\"");
+        let err = wait_for_display(&runner, time::Duration::ZERO, &state).unwrap_err();
+        assert!(matches!(err, AttentionError::DisplayTimeout { .. }));
+        assert_eq!(runner.calls(), ["xset q"]);
+    }
+}
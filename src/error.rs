@@ -0,0 +1,115 @@
+use core::time;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+
+pub enum AttentionError {
+    CommandSpawn { cmd: String, source: io::Error },
+    CommandFailed { cmd: String, stderr: String },
+    CommandTimeout { cmd: String },
+    UnexpectedOutput { cmd: String, reason: String },
+    WindowNotFound { app_name: String },
+    // Attaching found nothing, `what` says what was looked for.
+    NoMatchingWindow { what: String },
+    AppExited { app_name: String, status: ExitStatus },
+    WindowTimeout { app_name: String, timeout: time::Duration },
+    NoWindowsListed { timeout: time::Duration },
+    DisplayTimeout { timeout: time::Duration },
+    DurationTooLong { duration: time::Duration },
+    InvalidRegex { pattern: String, reason: String },
+    NoSession,
+    Inhibitor { reason: String },
+    UnknownProfile { name: String, known: Vec<String> },
+    Config { path: PathBuf, reason: String },
+    AppLog { path: PathBuf, source: io::Error },
+    LogFile { path: PathBuf, source: io::Error },
+    Install { path: PathBuf, source: io::Error },
+    MissingCommands(Vec<String>),
+    NotRunning,
+    AlreadyRunning { pid: Option<u32> },
+    Lock { path: PathBuf, source: io::Error },
+    Pidfile { path: PathBuf, source: io::Error },
+    Ipc { path: PathBuf, source: io::Error },
+    Dbus(zbus::Error),
+    SignalHandler(io::Error)
+}
+
+impl fmt::Display for AttentionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttentionError::CommandSpawn { cmd, source } => write!(f, "Failed to run {}: {}", cmd, source),
+            AttentionError::CommandFailed { cmd, stderr } => write!(f, "Command {} returned error: {}", cmd, stderr.trim()),
+            AttentionError::CommandTimeout { cmd } => write!(f, "Command {} didn't finish in time, killed it", cmd),
+            AttentionError::UnexpectedOutput { cmd, reason } => write!(f, "Couldn't make sense of {}'s output: {}", cmd, reason),
+            AttentionError::WindowNotFound { app_name } => write!(f, "{} exited before showing a window", app_name),
+            AttentionError::NoMatchingWindow { what } => write!(f, "No window matching {}", what),
+            AttentionError::AppExited { app_name, status } => write!(f, "{} died, {}", app_name, status),
+            AttentionError::WindowTimeout { app_name, timeout } => write!(f, "{} never showed a window within {}s", app_name, timeout.as_secs()),
+            AttentionError::NoWindowsListed { timeout } => write!(f, "wmctrl -lp listed no windows at all for {}s, the window manager doesn't seem to keep the EWMH client list (_NET_CLIENT_LIST) that attention needs", timeout.as_secs()),
+            AttentionError::DurationTooLong { duration } => write!(f, "{}s is too long to keep the screen on for", duration.as_secs()),
+            AttentionError::DisplayTimeout { timeout } => write!(f, "The X server didn't come back within {}s", timeout.as_secs()),
+            AttentionError::InvalidRegex { pattern, reason } => write!(f, "Invalid regex {}: {}", pattern, reason),
+            AttentionError::Config { path, reason } => write!(f, "Couldn't read {}: {}", path.display(), reason),
+            AttentionError::AppLog { path, source } => write!(f, "Couldn't open the app log {}: {}", path.display(), source),
+            AttentionError::LogFile { path, source } => write!(f, "Couldn't open the log file {}: {}", path.display(), source),
+            AttentionError::Install { path, source } => write!(f, "Couldn't install the service at {}: {}", path.display(), source),
+            AttentionError::UnknownProfile { name, known } if known.is_empty() => write!(f, "No profile named {}, the config doesn't define any", name),
+            AttentionError::UnknownProfile { name, known } => write!(f, "No profile named {}, the config has {}", name, known.join(", ")),
+            AttentionError::Inhibitor { reason } => write!(f, "Failed to keep the display on: {}", reason),
+            AttentionError::NoSession => write!(f, "No supported session found, X11 needs DISPLAY or --display, Wayland needs sway with WAYLAND_DISPLAY and SWAYSOCK or Hyprland"),
+            AttentionError::MissingCommands(missing) => {
+                write!(f, "Some commands attention needs aren't installed:")?;
+                missing.iter().try_for_each(|line| write!(f, "\n  install {}", line))
+            }
+            AttentionError::AlreadyRunning { pid: Some(pid) } => write!(f, "Another attention is already running as PID {}, pass --replace to take over", pid),
+            AttentionError::AlreadyRunning { pid: None } => write!(f, "Another attention is already running, pass --replace to take over"),
+            AttentionError::Lock { path, source } => write!(f, "Couldn't lock {}: {}", path.display(), source),
+            AttentionError::Pidfile { path, source } => write!(f, "Couldn't use the PID file {}: {}", path.display(), source),
+            AttentionError::NotRunning => write!(f, "No running attention instance to ask, it listens on $XDG_RUNTIME_DIR/attention.sock"),
+            AttentionError::Ipc { path, source } => write!(f, "Talking to {} failed: {}", path.display(), source),
+            AttentionError::Dbus(source) => write!(f, "D-Bus call failed: {}", source),
+            AttentionError::SignalHandler(source) => write!(f, "Failed to install the signal handler: {}", source)
+        }
+    }
+}
+
+impl AttentionError {
+    // What the process exits with, listed under --help. Clap's own usage
+    // errors exit with 2 as well.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AttentionError::InvalidRegex { .. } | AttentionError::Config { .. } | AttentionError::UnknownProfile { .. } | AttentionError::DurationTooLong { .. } => 2,
+            AttentionError::MissingCommands(_) => 3,
+            AttentionError::WindowNotFound { .. } | AttentionError::NoMatchingWindow { .. } | AttentionError::WindowTimeout { .. } | AttentionError::NoWindowsListed { .. } => 4,
+            AttentionError::CommandSpawn { .. } | AttentionError::CommandFailed { .. } | AttentionError::CommandTimeout { .. } | AttentionError::UnexpectedOutput { .. } => 5,
+            _ => 1
+        }
+    }
+}
+
+// `main` returning an error prints it with `Debug`, keep that to the one line
+// message rather than the derived struct dump.
+impl fmt::Debug for AttentionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl Error for AttentionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AttentionError::CommandSpawn { source, .. } => Some(source),
+            AttentionError::AppLog { source, .. } => Some(source),
+            AttentionError::LogFile { source, .. } => Some(source),
+            AttentionError::Install { source, .. } => Some(source),
+            AttentionError::Ipc { source, .. } => Some(source),
+            AttentionError::Lock { source, .. } => Some(source),
+            AttentionError::Pidfile { source, .. } => Some(source),
+            AttentionError::Dbus(source) => Some(source),
+            AttentionError::SignalHandler(source) => Some(source),
+            _ => None
+        }
+    }
+}
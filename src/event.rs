@@ -0,0 +1,119 @@
+use std::fmt;
+
+use chrono::{SecondsFormat, Utc};
+use log::info;
+use serde::Serialize;
+
+use crate::state::InhibitReason;
+use crate::style::{paint, Color};
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    InhibitOn { reason: InhibitReason, app: &'a str },
+    InhibitOff { app: &'a str },
+    AudioStarted { app: &'a str },
+    AudioStopped { app: &'a str },
+    PlaybackStarted { app: &'a str },
+    PlaybackStopped { app: &'a str },
+    FullscreenEntered { app: &'a str },
+    FullscreenLeft { app: &'a str },
+    WindowAdopted { app: &'a str, pid: u32 },
+    WindowClosed { app: &'a str },
+    WindowReopened { app: &'a str },
+    AppRestarting { app: &'a str, attempt: u32 },
+    UserIdle { app: &'a str, idle_secs: u64 },
+    UserBack { app: &'a str },
+    FocusLost { app: &'a str },
+    FocusGained { app: &'a str },
+    HoursEnded { app: &'a str },
+    HoursStarted { app: &'a str },
+    Unplugged { app: &'a str },
+    PluggedIn { app: &'a str },
+    DisplayLost,
+    DisplayBack { app: &'a str },
+    Paused { app: &'a str },
+    Resumed { app: &'a str },
+    Signal { signal: i32 },
+    ShuttingDown
+}
+
+impl fmt::Display for Event<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::InhibitOn { .. } => write!(f, "Turning off screen blanking.."),
+            Event::InhibitOff { .. } => write!(f, "Turning on screen blanking.."),
+            Event::AudioStarted { app } => write!(f, "{} is now playing audio..", app),
+            Event::AudioStopped { app } => write!(f, "{} is no longer playing audio..", app),
+            Event::PlaybackStarted { app } => write!(f, "{} is now playing..", app),
+            Event::PlaybackStopped { app } => write!(f, "{} is no longer playing..", app),
+            Event::FullscreenEntered { app } => write!(f, "{} is now fullscreen..", app),
+            Event::FullscreenLeft { app } => write!(f, "{} is no longer fullscreen..", app),
+            Event::WindowAdopted { app, pid } => write!(f, "{}'s window belongs to PID {}, tracking that instead..", app, pid),
+            Event::WindowClosed { app } => write!(f, "{}'s window is closed..", app),
+            Event::WindowReopened { app } => write!(f, "{}'s window is back..", app),
+            Event::AppRestarting { app, attempt } => write!(f, "{} died, starting it again (restart {})..", app, attempt),
+            Event::UserIdle { idle_secs, .. } => write!(f, "Idle for {}s, letting the screen blank..", idle_secs),
+            Event::UserBack { app } => write!(f, "Back from idle, {} gets the screen again..", app),
+            Event::FocusLost { app } => write!(f, "{} lost focus..", app),
+            Event::FocusGained { app } => write!(f, "{} has focus again..", app),
+            Event::HoursEnded { .. } => write!(f, "Outside the active hours, letting the screen blank.."),
+            Event::HoursStarted { app } => write!(f, "Active hours again, {} gets the screen again..", app),
+            Event::Unplugged { .. } => write!(f, "On battery, letting the screen blank.."),
+            Event::PluggedIn { app } => write!(f, "Back on AC, {} gets the screen again..", app),
+            Event::DisplayLost => write!(f, "The X server went away, waiting for it to come back.."),
+            Event::DisplayBack { app } => write!(f, "The X server is back, looking for {} again..", app),
+            Event::Paused { .. } => write!(f, "Paused, letting the screen blank.."),
+            Event::Resumed { app } => write!(f, "Resumed, {} gets the screen again..", app),
+            Event::Signal { signal } => write!(f, "Caught signal {}..", signal),
+            Event::ShuttingDown => write!(f, "Shutting down..")
+        }
+    }
+}
+
+impl Event<'_> {
+    // Blanking going off and back on is what's worth spotting in the log.
+    fn color(&self) -> Option<Color> {
+        match self {
+            Event::InhibitOn { .. } => Some(Color::Green),
+            Event::InhibitOff { .. } => Some(Color::Yellow),
+            _ => None
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct EventLine<'a> {
+    ts: String,
+    #[serde(flatten)]
+    event: Event<'a>
+}
+
+// Human readable by default, one JSON object per line with --json.
+pub(crate) fn report(json: bool, event: Event) {
+    if json {
+        let line = EventLine { ts: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true), event };
+        if let Ok(line) = serde_json::to_string(&line) {
+            println!("{}", line);
+        }
+    } else {
+        match event.color() {
+            Some(color) => info!("{}", paint(color, &event.to_string())),
+            None => info!("{}", event)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_serialize_flat_with_a_tag() {
+        let line = EventLine { ts: "2024-05-01T12:00:00.000Z".to_owned(), event: Event::InhibitOn { reason: InhibitReason::Audio, app: "mpv" } };
+        assert_eq!(
+            serde_json::to_string(&line).unwrap(),
+            r#"{"ts":"2024-05-01T12:00:00.000Z","event":"inhibit_on","reason":"audio","app":"mpv"}"#
+        );
+    }
+}
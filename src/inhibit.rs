@@ -0,0 +1,827 @@
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use clap::ValueEnum;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use zbus::blocking::Connection;
+#[cfg(unix)]
+use zbus::zvariant;
+
+use crate::error::AttentionError;
+use crate::event::{report, Event};
+use crate::instance::remove_pidfile;
+use crate::ipc::remove_socket;
+use crate::runner::{run_command, CommandRunner, DryRunner};
+use crate::saved::{remove_state_file, save};
+use crate::session::SessionType;
+use crate::state::{lock, InhibitReason, ScreenBlankingState, State};
+use crate::window::stop_app;
+
+#[derive(PartialEq, Eq, Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InhibitMethod {
+    Xset,
+    Dbus,
+    Logind,
+    Gnome,
+    #[serde(rename = "xdg-screensaver")]
+    XdgScreensaver,
+    Reset
+}
+
+impl InhibitMethod {
+    fn name(self) -> &'static str {
+        match self {
+            InhibitMethod::Xset => "xset",
+            InhibitMethod::Dbus => "D-Bus",
+            InhibitMethod::Logind => "logind",
+            InhibitMethod::Gnome => "GNOME session",
+            InhibitMethod::XdgScreensaver => "xdg-screensaver",
+            InhibitMethod::Reset => "reset"
+        }
+    }
+}
+
+// How much gets held off on top of the display: idle also stops logind's
+// idle action, lock the screensaver and its lock, all both and suspend too.
+#[derive(PartialEq, Eq, Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InhibitScope {
+    Display,
+    Idle,
+    Lock,
+    All
+}
+
+// What `InhibitScope` took besides the inhibit method, so releasing gives
+// back exactly that. The buses are connected to through `connect`, which
+// tests swap for one that never gets there.
+pub struct ScopeInhibits {
+    logind_lock: Option<LogindLock>,
+    screensaver: Option<(Connection, u32)>,
+    connect: fn(Bus) -> Result<Connection, zbus::Error>
+}
+
+impl Default for ScopeInhibits {
+    fn default() -> Self {
+        Self { logind_lock: None, screensaver: None, connect: connect_bus }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Bus {
+    System,
+    Session
+}
+
+fn connect_bus(bus: Bus) -> Result<Connection, zbus::Error> {
+    match bus {
+        Bus::System => Connection::system(),
+        Bus::Session => Connection::session()
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical
+}
+
+impl Urgency {
+    fn as_str(self) -> &'static str {
+        match self {
+            Urgency::Low => "low",
+            Urgency::Normal => "normal",
+            Urgency::Critical => "critical"
+        }
+    }
+}
+
+pub(crate) const DEFAULT_ON_TEXT: &str = "⚠️ Power Management is inhibited by {app} ({reason})";
+pub(crate) const DEFAULT_OFF_TEXT: &str = "⚠️ Power Management is back to normal";
+pub(crate) const DEFAULT_REASON_TEXT: &str = "attention: {app} active";
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DpmsSettings {
+    pub standby: u32,
+    pub suspend: u32,
+    pub off: u32,
+    pub enabled: bool
+}
+
+// Picks the timeouts out of the DPMS section of `xset q`:
+//
+//   DPMS (Energy Star):
+//     Standby: 600    Suspend: 600    Off: 600
+//     DPMS is Enabled
+pub(crate) fn parse_dpms_settings(stdout: &str) -> Option<DpmsSettings> {
+    let section = &stdout[stdout.find("DPMS (Energy Star):")?..];
+
+    let timeout = |name: &str| -> Option<u32> {
+        let start = section.find(name)? + name.len();
+        section[start..].split_whitespace().next()?.parse().ok()
+    };
+
+    Some(DpmsSettings {
+        standby: timeout("Standby:")?,
+        suspend: timeout("Suspend:")?,
+        off: timeout("Off:")?,
+        enabled: section.contains("DPMS is Enabled")
+    })
+}
+
+pub(crate) fn read_dpms_settings(runner: &dyn CommandRunner) -> Result<Option<DpmsSettings>, AttentionError> {
+    let output = run_command(runner, "xset", &["q"])?;
+    Ok(parse_dpms_settings(&String::from_utf8_lossy(&output.stdout)))
+}
+
+pub(crate) fn restore_dpms_settings(runner: &dyn CommandRunner, dpms: &DpmsSettings) -> Result<(), AttentionError> {
+    let (standby, suspend, off) = (dpms.standby.to_string(), dpms.suspend.to_string(), dpms.off.to_string());
+    run_command(runner, "xset", &["dpms", &standby, &suspend, &off])?;
+    run_command(runner, "xset", &[if dpms.enabled { "+dpms" } else { "-dpms" }])?;
+    Ok(())
+}
+
+// On sway the inhibitor is attached to the tracked window through
+// `inhibit_idle`, and goes away on its own if the window does.
+pub(crate) fn set_idle_inhibit(runner: &dyn CommandRunner, state: &State, inhibit: bool) -> Result<(), AttentionError> {
+    match (state.session, &state.window) {
+        (SessionType::Sway, Some(window)) => {
+            let mode = if inhibit { "open" } else { "none" };
+            let result = run_command(runner, "swaymsg", &[&format!("[con_id={}]", window.id), "inhibit_idle", mode]);
+            // Releasing fails when the window is already gone, which took
+            // the inhibitor with it anyway.
+            if inhibit {
+                result?;
+            }
+        }
+        _ => match &state.dpms {
+            Some(dpms) if !inhibit => restore_dpms_settings(runner, dpms)?,
+            _ => {
+                run_command(runner, "xset", &[if inhibit { "-dpms" } else { "+dpms" }])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// What the inhibitors taken over D-Bus say they're for, which is what
+// `systemd-inhibit --list` and the desktop's "preventing sleep" list show.
+// `{app}` and `{reason}` are filled in like in the notifications, with the
+// reason the inhibit was first taken for.
+fn inhibit_text(state: &State, app_name: &str, reason: &str) -> String {
+    state.reason.replace("{app}", app_name).replace("{reason}", reason)
+}
+
+fn screensaver_inhibit(connection: &Connection, app_name: &str, why: &str) -> Result<u32, AttentionError> {
+    connection.call_method(
+        Some("org.freedesktop.ScreenSaver"),
+        "/org/freedesktop/ScreenSaver",
+        Some("org.freedesktop.ScreenSaver"),
+        "Inhibit",
+        &(app_name, why)
+    )
+    .and_then(|reply| reply.body().deserialize())
+    .map_err(AttentionError::Dbus)
+}
+
+fn screensaver_release(connection: &Connection, cookie: u32) -> Result<(), AttentionError> {
+    connection.call_method(
+        Some("org.freedesktop.ScreenSaver"),
+        "/org/freedesktop/ScreenSaver",
+        Some("org.freedesktop.ScreenSaver"),
+        "UnInhibit",
+        &(cookie,)
+    )
+    .map_err(AttentionError::Dbus)?;
+    Ok(())
+}
+
+// The screensaver drops our inhibit if the connection goes away, so the
+// connection lives in `State` for as long as the cookie does.
+pub(crate) fn dbus_inhibit(app_name: &str, why: &str, state: &mut State) -> Result<(), AttentionError> {
+    let connection = match state.dbus.take() {
+        Some(connection) => connection,
+        None => Connection::session().map_err(AttentionError::Dbus)?
+    };
+
+    let cookie = screensaver_inhibit(&connection, app_name, why);
+    state.dbus = Some(connection);
+    state.dbus_cookie = Some(cookie?);
+    Ok(())
+}
+
+pub(crate) fn dbus_release(state: &mut State) -> Result<(), AttentionError> {
+    if let (Some(connection), Some(cookie)) = (&state.dbus, state.dbus_cookie.take()) {
+        screensaver_release(connection, cookie)?;
+    }
+    Ok(())
+}
+
+const GNOME_SESSION: &str = "org.gnome.SessionManager";
+// GsmInhibitorFlag, only idle is asked for.
+const GNOME_INHIBIT_IDLE: u32 = 8;
+
+// On GNOME Wayland the session manager is asked instead of xset.
+pub(crate) fn gnome_session_running(connection: &Connection) -> bool {
+    let reply =
+    connection.call_method(
+        Some("org.freedesktop.DBus"),
+        "/org/freedesktop/DBus",
+        Some("org.freedesktop.DBus"),
+        "NameHasOwner",
+        &(GNOME_SESSION,)
+    );
+    reply.and_then(|reply| reply.body().deserialize()).unwrap_or(false)
+}
+
+// The session manager keeps the inhibit for as long as the connection lives,
+// like the screensaver, the cookie goes into the same slot.
+pub(crate) fn gnome_inhibit(app_name: &str, why: &str, state: &mut State) -> Result<(), AttentionError> {
+    let connection = match state.dbus.take() {
+        Some(connection) => connection,
+        None => Connection::session().map_err(AttentionError::Dbus)?
+    };
+
+    let reply =
+    connection.call_method(
+        Some(GNOME_SESSION),
+        "/org/gnome/SessionManager",
+        Some(GNOME_SESSION),
+        "Inhibit",
+        &(app_name, 0u32, why, GNOME_INHIBIT_IDLE)
+    );
+    state.dbus = Some(connection);
+
+    let cookie: u32 = reply.and_then(|reply| reply.body().deserialize()).map_err(AttentionError::Dbus)?;
+    state.dbus_cookie = Some(cookie);
+    Ok(())
+}
+
+pub(crate) fn gnome_release(state: &mut State) -> Result<(), AttentionError> {
+    if let (Some(connection), Some(cookie)) = (&state.dbus, state.dbus_cookie.take()) {
+        connection.call_method(
+            Some(GNOME_SESSION),
+            "/org/gnome/SessionManager",
+            Some(GNOME_SESSION),
+            "Uninhibit",
+            &(cookie,)
+        )
+        .map_err(AttentionError::Dbus)?;
+    }
+    Ok(())
+}
+
+// xdg-screensaver ties the suspend to an X window and drops it by itself once
+// that window is gone. The id is kept so a reopened window doesn't get
+// resumed in place of the one that was suspended.
+pub(crate) fn xdg_screensaver_suspend(runner: &dyn CommandRunner, state: &mut State) -> Result<(), AttentionError> {
+    let Some(window) = &state.window else {
+        warn!("There's no window for xdg-screensaver to suspend the screensaver for..");
+        return Ok(());
+    };
+    run_command(runner, "xdg-screensaver", &["suspend", &window.id])?;
+    state.xdg_suspended = Some(window.id.clone());
+    Ok(())
+}
+
+pub(crate) fn xdg_screensaver_resume(runner: &dyn CommandRunner, state: &mut State) {
+    // Failing means the window took the suspend with it already.
+    if let Some(window_id) = state.xdg_suspended.take()
+        && let Err(err) = run_command(runner, "xdg-screensaver", &["resume", &window_id]) {
+        debug!("{}", err);
+    }
+}
+
+// With --inhibit-method reset nothing is turned off, the idle timer is just
+// reset every poll for as long as blanking is held. If we die the user's own
+// timeout takes over from there.
+pub(crate) fn heartbeat(runner: &dyn CommandRunner, state: &State) -> Result<(), AttentionError> {
+    if state.inhibit_method == InhibitMethod::Reset && state.last_screen_blanking_state == ScreenBlankingState::Off && !state.dry_run {
+        run_command(runner, "xset", &["s", "reset"])?;
+    }
+    Ok(())
+}
+
+// logind hands back a file descriptor, the idle lock lasts for as long as it's
+// open, so releasing is just dropping it.
+#[cfg(unix)]
+pub type LogindLock = std::os::fd::OwnedFd;
+
+// Without file descriptors there's no logind either, so no lock is ever held.
+#[cfg(not(unix))]
+pub type LogindLock = std::convert::Infallible;
+
+#[cfg(unix)]
+fn logind_lock(connection: &Connection, what: &str, app_name: &str, why: &str) -> Result<LogindLock, AttentionError> {
+    let fd: zvariant::OwnedFd =
+    connection.call_method(
+        Some("org.freedesktop.login1"),
+        "/org/freedesktop/login1",
+        Some("org.freedesktop.login1.Manager"),
+        "Inhibit",
+        &(what, app_name, why, "block")
+    )
+    .and_then(|reply| reply.body().deserialize())
+    .map_err(AttentionError::Dbus)?;
+    Ok(fd.into())
+}
+
+#[cfg(not(unix))]
+fn logind_lock(_connection: &Connection, _what: &str, _app_name: &str, _why: &str) -> Result<LogindLock, AttentionError> {
+    Err(AttentionError::Dbus(zbus::Error::Unsupported))
+}
+
+pub(crate) fn logind_inhibit(app_name: &str, why: &str, state: &mut State) -> Result<(), AttentionError> {
+    let connection = match state.dbus.take() {
+        Some(connection) => connection,
+        None => Connection::system().map_err(AttentionError::Dbus)?
+    };
+
+    let lock = logind_lock(&connection, "idle", app_name, why);
+    state.dbus = Some(connection);
+    state.logind_lock = Some(lock?);
+    Ok(())
+}
+
+// Whatever the inhibit method already covers isn't taken twice.
+fn take_scope_inhibits(app_name: &str, why: &str, state: &mut State) -> Result<(), AttentionError> {
+    let what = match state.inhibit_scope {
+        InhibitScope::Idle if state.inhibit_method != InhibitMethod::Logind => Some("idle"),
+        InhibitScope::All => Some("idle:sleep"),
+        _ => None
+    };
+    if let Some(what) = what {
+        let connection = (state.scope_inhibits.connect)(Bus::System).map_err(AttentionError::Dbus)?;
+        state.scope_inhibits.logind_lock = Some(logind_lock(&connection, what, app_name, why)?);
+    }
+    if matches!(state.inhibit_scope, InhibitScope::Lock | InhibitScope::All) && state.inhibit_method != InhibitMethod::Dbus {
+        let connection = (state.scope_inhibits.connect)(Bus::Session).map_err(AttentionError::Dbus)?;
+        let cookie = screensaver_inhibit(&connection, app_name, why)?;
+        state.scope_inhibits.screensaver = Some((connection, cookie));
+    }
+    Ok(())
+}
+
+fn release_scope_inhibits(state: &mut State) -> Result<(), AttentionError> {
+    state.scope_inhibits.logind_lock = None;
+    if let Some((connection, cookie)) = state.scope_inhibits.screensaver.take() {
+        screensaver_release(&connection, cookie)?;
+    }
+    Ok(())
+}
+
+// The notification is only a courtesy, failing to send it mustn't stop the
+// inhibit or release that comes after it. `{app}` in the text is replaced
+// with the app's name and `{reason}` with what blanking is, or was, held for.
+// With --notify-cooldown a flapping app only gets one notification per
+// direction that often, the inhibit or release itself still happens. With
+// --notify-replace notify-send prints the id of what it sent and the next
+// one takes its place.
+fn notify(runner: &dyn CommandRunner, state: &mut State, inhibited: bool, app_name: &str, reason: &str) {
+    if !state.notify {
+        return;
+    }
+    let last = if inhibited { &mut state.notified_on_at } else { &mut state.notified_off_at };
+    if last.is_some_and(|at| at.elapsed() < state.notify_cooldown) {
+        debug!("Not notifying again within --notify-cooldown..");
+        return;
+    }
+    *last = Some(Instant::now());
+
+    let template = if inhibited { &state.notify_on_text } else { &state.notify_off_text };
+    let mut message = template.replace("{app}", app_name).replace("{reason}", reason);
+    if state.dry_run {
+        message.insert_str(0, "[dry-run] ");
+    }
+    let timeout = state.notify_timeout.map(|timeout| timeout.as_millis().to_string());
+    let replaces = state.notification_id.map(|id| id.to_string());
+    let mut args = Vec::new();
+    if let Some(icon) = &state.notify_icon {
+        args.extend(["-i", icon.as_str()]);
+    }
+    if let Some(urgency) = state.notify_urgency {
+        args.extend(["-u", urgency.as_str()]);
+    }
+    if let Some(timeout) = &timeout {
+        args.extend(["-t", timeout.as_str()]);
+    }
+    if state.notify_replace {
+        args.push("-p");
+        if let Some(id) = &replaces {
+            args.extend(["-r", id.as_str()]);
+        }
+    }
+    args.push(&message);
+
+    match run_command(runner, "notify-send", &args) {
+        Ok(output) if state.notify_replace => state.notification_id = String::from_utf8_lossy(&output.stdout).trim().parse().ok(),
+        Ok(_) => (),
+        Err(err) => warn!("Couldn't send the notification: {}..", err)
+    }
+}
+
+// Hooks run through the shell and detached, a slow one mustn't hold up the
+// loop, its exit status only shows up with --verbose.
+fn run_hook(state: &State, hook: Option<&String>, app_name: &str, reason: &str) {
+    let Some(hook) = hook else {
+        return;
+    };
+    if state.dry_run {
+        info!("[dry-run] would run hook: {}", hook);
+        return;
+    }
+
+    let child = Command::new("sh")
+    .args(["-c", hook])
+    .env("ATTENTION_APP", app_name)
+    .env("ATTENTION_REASON", reason)
+    .stdin(Stdio::null())
+    .spawn();
+    match child {
+        Ok(mut child) => {
+            let hook = hook.clone();
+            thread::spawn(move || match child.wait() {
+                Ok(status) => debug!("Hook {} exited with {}", hook, status),
+                Err(err) => warn!("Couldn't wait for hook {}: {}..", hook, err)
+            });
+        }
+        Err(err) => warn!("Couldn't run hook {}: {}..", hook, err)
+    }
+}
+
+pub(crate) fn turn_off_screen_blanking(runner: &dyn CommandRunner, app_name: &str, reason: InhibitReason, state: &mut State) -> Result<(), AttentionError> {
+    state.inhibit_reasons.insert(reason);
+    if state.last_screen_blanking_state == ScreenBlankingState::On && !state.idle && !state.unfocused && !state.off_hours && !state.on_battery && !state.manual_override {
+        report(state.json, Event::InhibitOn { reason, app: app_name });
+
+        notify(runner, state, true, app_name, reason.as_str());
+        let why = inhibit_text(state, app_name, reason.as_str());
+        match state.inhibitor.as_mut() {
+            Some(inhibitor) if !state.dry_run => inhibitor.inhibit(app_name)?,
+            _ => match state.inhibit_method {
+                InhibitMethod::Xset if state.dry_run => set_idle_inhibit(&DryRunner, state, true)?,
+                InhibitMethod::Xset => set_idle_inhibit(runner, state, true)?,
+                _ if state.dry_run => info!("[dry-run] would take a {} inhibitor", state.inhibit_method.name()),
+                InhibitMethod::Dbus => dbus_inhibit(app_name, &why, state)?,
+                InhibitMethod::Logind => logind_inhibit(app_name, &why, state)?,
+                InhibitMethod::Gnome => gnome_inhibit(app_name, &why, state)?,
+                InhibitMethod::XdgScreensaver => xdg_screensaver_suspend(runner, state)?,
+                InhibitMethod::Reset => {
+                    run_command(runner, "xset", &["s", "reset"])?;
+                }
+            }
+        }
+        // Off as soon as the display is, so a scope inhibit failing after it
+        // still leaves the release to put DPMS back.
+        state.last_screen_blanking_state = ScreenBlankingState::Off;
+        state.metrics.inhibited();
+        save(state);
+
+        match state.inhibit_scope {
+            InhibitScope::Display => {}
+            _ if state.dry_run => info!("[dry-run] would also hold off idle actions, the lock or suspend"),
+            _ => take_scope_inhibits(app_name, &why, state)?
+        }
+        run_hook(state, state.on_inhibit.as_ref(), app_name, reason.as_str());
+    }
+    Ok(())
+}
+
+pub(crate) fn turn_on_screen_blanking(runner: &dyn CommandRunner, state: &mut State) -> Result<(), AttentionError> {
+    if state.last_screen_blanking_state == ScreenBlankingState::Off {
+        report(state.json, Event::InhibitOff { app: &state.app_name });
+
+        let mut reasons: Vec<&str> = state.inhibit_reasons.iter().map(|reason| reason.as_str()).collect();
+        reasons.sort_unstable();
+        let reasons = reasons.join(",");
+        let app_name = state.app_name.clone();
+        notify(runner, state, false, &app_name, &reasons);
+        match state.inhibitor.as_mut() {
+            Some(inhibitor) if !state.dry_run => inhibitor.release()?,
+            _ => match state.inhibit_method {
+                InhibitMethod::Xset if state.dry_run => set_idle_inhibit(&DryRunner, state, false)?,
+                InhibitMethod::Xset => set_idle_inhibit(runner, state, false)?,
+                _ if state.dry_run => info!("[dry-run] would release the {} inhibitor", state.inhibit_method.name()),
+                InhibitMethod::Dbus => dbus_release(state)?,
+                InhibitMethod::Logind => state.logind_lock = None,
+                InhibitMethod::Gnome => gnome_release(state)?,
+                InhibitMethod::XdgScreensaver => xdg_screensaver_resume(runner, state),
+                InhibitMethod::Reset => {}
+            }
+        }
+        // The display is back either way, a scope inhibit that can't be
+        // given back is only reported.
+        let scope = release_scope_inhibits(state);
+
+        state.last_screen_blanking_state = ScreenBlankingState::On;
+        state.metrics.released();
+        save(state);
+        run_hook(state, state.on_release.as_ref(), &state.app_name, &reasons);
+        state.inhibit_reasons.clear();
+        return scope;
+    }
+    state.inhibit_reasons.clear();
+    Ok(())
+}
+
+// Gives blanking back when dropped, so leaving `main` by returning, erroring
+// or unwinding from a panic can't leave the screen stuck awake. It also
+// stops the launched app with --kill-on-exit.
+pub struct BlankingGuard(Arc<Mutex<State>>);
+
+impl BlankingGuard {
+    pub fn new(state: Arc<Mutex<State>>) -> Self {
+        Self(state)
+    }
+}
+
+impl Drop for BlankingGuard {
+    fn drop(&mut self) {
+        let mut state = lock(&self.0);
+        let runner = state.runner.clone();
+        if state.last_screen_blanking_state == ScreenBlankingState::Off
+            && !state.keep_on_exit
+            && let Err(err) = turn_on_screen_blanking(&runner, &mut state) {
+            error!("{}", err);
+        }
+        stop_app(&runner, &mut state);
+        remove_socket(&mut state);
+        remove_pidfile(&mut state);
+        if !state.keep_on_exit {
+            remove_state_file(&mut state);
+        }
+    }
+}
+
+// Drops a single reason, blanking only comes back once nothing else is
+// still holding it off.
+pub(crate) fn release_screen_blanking(runner: &dyn CommandRunner, reason: InhibitReason, state: &mut State) -> Result<(), AttentionError> {
+    state.inhibit_reasons.remove(&reason);
+    if state.inhibit_reasons.is_empty() && state.reasons_elsewhere().next().is_none() && !in_startup_grace(state) {
+        turn_on_screen_blanking(runner, state)?;
+    }
+    Ok(())
+}
+
+// With --startup-grace a freshly shown window gets a while to start playing
+// before blanking is given back.
+pub(crate) fn in_startup_grace(state: &State) -> bool {
+    state.window_shown_at.is_some_and(|shown_at| shown_at.elapsed() < state.startup_grace)
+}
+
+// Gives back what the grace held on to once it's over and nothing wants the
+// screen on anymore.
+pub(crate) fn end_startup_grace(runner: &dyn CommandRunner, state: &mut State) -> Result<(), AttentionError> {
+    if state.window_shown_at.is_none() || in_startup_grace(state) {
+        return Ok(());
+    }
+    state.window_shown_at = None;
+    if state.inhibit_reasons.is_empty() && state.reasons_elsewhere().next().is_none() {
+        turn_on_screen_blanking(runner, state)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time;
+    use std::time::Instant;
+
+    use super::*;
+    use crate::inhibitor::Inhibitor;
+    use crate::runner::MockRunner;
+    use crate::window::{mpv_window, TrackedWindow};
+
+    const XSET_Q: &str = "\
+Keyboard Control:
+  auto repeat:  on    key click percent:  0    LED mask:  00000000
+Screen Saver:
+  prefer blanking:  yes    allow exposures:  yes
+  timeout:  600    cycle:  600
+DPMS (Energy Star):
+  Standby: 300    Suspend: 600    Off: 900
+  DPMS is Enabled
+  Monitor is On
+";
+
+    #[test]
+    fn dpms_settings_are_parsed_from_xset() {
+        let runner = MockRunner::new().with("xset", XSET_Q);
+        let dpms = read_dpms_settings(&runner).unwrap();
+        assert_eq!(dpms, Some(DpmsSettings { standby: 300, suspend: 600, off: 900, enabled: true }));
+
+        let disabled = XSET_Q.replace("DPMS is Enabled", "DPMS is Disabled");
+        assert!(!parse_dpms_settings(&disabled).unwrap().enabled);
+        assert_eq!(parse_dpms_settings("Server does not have the DPMS Extension\n"), None);
+    }
+
+    #[test]
+    fn restoring_reapplies_the_captured_timeouts() {
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        state.last_screen_blanking_state = ScreenBlankingState::Off;
+        state.dpms = Some(DpmsSettings { standby: 300, suspend: 600, off: 900, enabled: true });
+        turn_on_screen_blanking(&runner, &mut state).unwrap();
+        assert_eq!(runner.calls(), vec![
+            "notify-send ⚠️ Power Management is back to normal",
+            "xset dpms 300 600 900",
+            "xset +dpms"
+        ]);
+    }
+
+    struct CountingInhibitor(Arc<Mutex<Vec<String>>>);
+
+    impl Inhibitor for CountingInhibitor {
+        fn inhibit(&mut self, app_name: &str) -> Result<(), AttentionError> {
+            self.0.lock().unwrap().push(format!("inhibit {}", app_name));
+            Ok(())
+        }
+
+        fn release(&mut self) -> Result<(), AttentionError> {
+            self.0.lock().unwrap().push("release".to_owned());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn a_custom_inhibitor_replaces_the_inhibit_method() {
+        let runner = MockRunner::new();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        state.inhibitor = Some(Box::new(CountingInhibitor(calls.clone())));
+        turn_off_screen_blanking(&runner, "mpv", InhibitReason::Audio, &mut state).unwrap();
+        turn_on_screen_blanking(&runner, &mut state).unwrap();
+        assert!(runner.calls().is_empty());
+        assert_eq!(*calls.lock().unwrap(), vec!["inhibit mpv", "release"]);
+    }
+
+    #[test]
+    fn blanking_stays_off_through_the_startup_grace() {
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        state.startup_grace = time::Duration::from_secs(60);
+        state.window_shown_at = Some(Instant::now());
+        turn_off_screen_blanking(&runner, "mpv", InhibitReason::Audio, &mut state).unwrap();
+        release_screen_blanking(&runner, InhibitReason::Audio, &mut state).unwrap();
+        end_startup_grace(&runner, &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::Off);
+
+        state.window_shown_at = Instant::now().checked_sub(time::Duration::from_secs(61));
+        end_startup_grace(&runner, &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::On);
+        assert_eq!(runner.calls(), vec!["xset -dpms", "xset +dpms"]);
+    }
+
+    #[test]
+    fn xdg_screensaver_resumes_the_window_it_suspended() {
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        state.inhibit_method = InhibitMethod::XdgScreensaver;
+        state.window = Some(mpv_window());
+        turn_off_screen_blanking(&runner, "mpv", InhibitReason::Audio, &mut state).unwrap();
+        state.window = Some(TrackedWindow { id: "0x04c00002".to_owned(), ..mpv_window() });
+        turn_on_screen_blanking(&runner, &mut state).unwrap();
+        assert_eq!(runner.calls(), vec!["xdg-screensaver suspend 0x03a00006", "xdg-screensaver resume 0x03a00006"]);
+    }
+
+    #[test]
+    fn reset_keeps_poking_the_idle_timer_while_held() {
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        state.inhibit_method = InhibitMethod::Reset;
+        heartbeat(&runner, &state).unwrap();
+        turn_off_screen_blanking(&runner, "mpv", InhibitReason::Audio, &mut state).unwrap();
+        heartbeat(&runner, &state).unwrap();
+        turn_on_screen_blanking(&runner, &mut state).unwrap();
+        heartbeat(&runner, &state).unwrap();
+        assert_eq!(runner.calls(), vec!["xset s reset", "xset s reset"]);
+    }
+
+    #[test]
+    fn a_failing_scope_inhibit_still_gets_dpms_back() {
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        state.inhibit_scope = InhibitScope::All;
+        state.scope_inhibits.connect = |_| Err(zbus::Error::Failure("no bus here".to_owned()));
+        assert!(turn_off_screen_blanking(&runner, "mpv", InhibitReason::Audio, &mut state).is_err());
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::Off);
+        turn_on_screen_blanking(&runner, &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::On);
+        assert_eq!(runner.calls(), ["xset -dpms", "xset +dpms"]);
+    }
+
+    #[test]
+    fn a_dry_run_takes_nothing_beyond_the_display() {
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        state.dry_run = true;
+        state.inhibit_scope = InhibitScope::All;
+        turn_off_screen_blanking(&runner, "mpv", InhibitReason::Audio, &mut state).unwrap();
+        assert!(state.scope_inhibits.logind_lock.is_none() && state.scope_inhibits.screensaver.is_none());
+        turn_on_screen_blanking(&runner, &mut state).unwrap();
+        assert!(runner.calls().is_empty());
+    }
+
+    #[test]
+    fn notifications_are_throttled_but_the_inhibit_isnt() {
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        state.notify_cooldown = time::Duration::from_secs(60);
+        for _ in 0..2 {
+            turn_off_screen_blanking(&runner, "mpv", InhibitReason::Audio, &mut state).unwrap();
+            turn_on_screen_blanking(&runner, &mut state).unwrap();
+        }
+        assert_eq!(runner.calls(), vec![
+            "notify-send ⚠️ Power Management is inhibited by mpv (audio)",
+            "xset -dpms",
+            "notify-send ⚠️ Power Management is back to normal",
+            "xset +dpms",
+            "xset -dpms",
+            "xset +dpms"
+        ]);
+    }
+
+    #[test]
+    fn a_failing_notification_doesnt_stop_the_inhibit() {
+        let runner = MockRunner::new().failing("notify-send", "Cannot autolaunch D-Bus without X11 $DISPLAY");
+        let mut state = State::new(SessionType::X11);
+        turn_off_screen_blanking(&runner, "mpv", InhibitReason::Audio, &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::Off);
+        assert_eq!(runner.calls(), vec!["notify-send ⚠️ Power Management is inhibited by mpv (audio)", "xset -dpms"]);
+    }
+
+    #[test]
+    fn notifications_use_the_configured_text_icon_and_urgency() {
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        state.notify_on_text = "{app} keeps the screen on".to_owned();
+        state.notify_icon = Some("video-display".to_owned());
+        state.notify_urgency = Some(Urgency::Low);
+        turn_off_screen_blanking(&runner, "mpv", InhibitReason::Audio, &mut state).unwrap();
+        assert_eq!(runner.calls()[0], "notify-send -i video-display -u low mpv keeps the screen on");
+    }
+
+    #[test]
+    fn a_replacing_notification_takes_the_place_of_the_last_one() {
+        let runner = MockRunner::new().with("notify-send", "41\n");
+        let mut state = State::new(SessionType::X11);
+        state.notify_timeout = Some(time::Duration::from_secs(5));
+        state.notify_replace = true;
+        turn_off_screen_blanking(&runner, "mpv", InhibitReason::Audio, &mut state).unwrap();
+        turn_on_screen_blanking(&runner, &mut state).unwrap();
+        assert_eq!(runner.calls(), vec![
+            "notify-send -t 5000 -p ⚠️ Power Management is inhibited by mpv (audio)",
+            "xset -dpms",
+            "notify-send -t 5000 -p -r 41 ⚠️ Power Management is back to normal",
+            "xset +dpms"
+        ]);
+    }
+
+    #[test]
+    fn dry_run_notifies_but_leaves_dpms_alone() {
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        state.dry_run = true;
+        turn_off_screen_blanking(&runner, "mpv", InhibitReason::Audio, &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::Off);
+        assert_eq!(runner.calls(), vec!["notify-send [dry-run] ⚠️ Power Management is inhibited by mpv (audio)"]);
+    }
+
+    #[test]
+    fn the_inhibit_text_names_the_app_and_reason() {
+        let mut state = State::new(SessionType::X11);
+        assert_eq!(inhibit_text(&state, "mpv", "audio"), "attention: mpv active");
+        state.reason = "{app} is keeping the screen on for {reason}".to_owned();
+        assert_eq!(inhibit_text(&state, "mpv", "audio"), "mpv is keeping the screen on for audio");
+    }
+
+    #[test]
+    fn hooks_see_the_app_and_reason() {
+        let out = std::env::temp_dir().join(format!("attention-hook-{}", std::process::id()));
+        let mut state = State::new(SessionType::X11);
+        state.on_inhibit = Some(format!("echo \"$ATTENTION_APP $ATTENTION_REASON\" > {}", out.display()));
+        turn_off_screen_blanking(&MockRunner::new(), "mpv", InhibitReason::Fullscreen, &mut state).unwrap();
+
+        for _ in 0..50 {
+            if let Ok(contents) = std::fs::read_to_string(&out) && !contents.is_empty() {
+                let _ = std::fs::remove_file(&out);
+                assert_eq!(contents.trim(), "mpv fullscreen");
+                return;
+            }
+            thread::sleep(std::time::Duration::from_millis(20));
+        }
+        panic!("the hook never ran");
+    }
+}
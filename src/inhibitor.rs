@@ -0,0 +1,77 @@
+use crate::error::AttentionError;
+
+// A way of keeping the display on that doesn't fit the built in methods, set
+// `State::inhibitor` and it's used instead of `--inhibit-method`.
+pub trait Inhibitor {
+    fn inhibit(&mut self, app_name: &str) -> Result<(), AttentionError>;
+    fn release(&mut self) -> Result<(), AttentionError>;
+}
+
+// Windows keeps the display on for as long as the thread asks for it, which
+// is the tracking thread here. Only the inhibit backend exists for Windows
+// so far, finding windows and audio streams is still X11/sway only.
+#[cfg(windows)]
+pub struct ExecutionStateInhibitor;
+
+#[cfg(windows)]
+impl ExecutionStateInhibitor {
+    fn set(flags: windows_sys::Win32::System::Power::EXECUTION_STATE) -> Result<(), AttentionError> {
+        // Zero means the call failed, anything else is the previous state.
+        if unsafe { windows_sys::Win32::System::Power::SetThreadExecutionState(flags) } == 0 {
+            return Err(AttentionError::Inhibitor { reason: std::io::Error::last_os_error().to_string() });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+impl Inhibitor for ExecutionStateInhibitor {
+    fn inhibit(&mut self, _app_name: &str) -> Result<(), AttentionError> {
+        use windows_sys::Win32::System::Power::{ES_CONTINUOUS, ES_DISPLAY_REQUIRED};
+        Self::set(ES_CONTINUOUS | ES_DISPLAY_REQUIRED)
+    }
+
+    fn release(&mut self) -> Result<(), AttentionError> {
+        Self::set(windows_sys::Win32::System::Power::ES_CONTINUOUS)
+    }
+}
+
+// macOS holds a display sleep assertion for as long as `caffeinate -d` runs,
+// so inhibiting is spawning it and releasing is killing it.
+#[cfg(target_os = "macos")]
+#[derive(Default)]
+pub struct CaffeinateInhibitor {
+    child: Option<std::process::Child>
+}
+
+#[cfg(target_os = "macos")]
+impl Inhibitor for CaffeinateInhibitor {
+    fn inhibit(&mut self, _app_name: &str) -> Result<(), AttentionError> {
+        if self.child.is_none() {
+            let child = std::process::Command::new("caffeinate")
+            .arg("-d")
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .map_err(|source| AttentionError::CommandSpawn { cmd: "caffeinate".to_owned(), source })?;
+            self.child = Some(child);
+        }
+        Ok(())
+    }
+
+    fn release(&mut self) -> Result<(), AttentionError> {
+        if let Some(mut child) = self.child.take() {
+            // It may already be gone if someone killed it, that's released too.
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl Drop for CaffeinateInhibitor {
+    fn drop(&mut self) {
+        let _ = self.release();
+    }
+}
@@ -0,0 +1,172 @@
+use core::time;
+use std::env;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::thread;
+use std::time::Instant;
+
+use log::info;
+
+use crate::error::AttentionError;
+use crate::procfs::is_alive;
+use crate::runner::{run_command, CommandRunner};
+use crate::state::State;
+
+// Set on the copy --detach starts, which is the one that keeps running.
+const DETACHED: &str = "ATTENTION_DETACHED";
+
+// Two instances toggling DPMS against each other never settle, so the first
+// one holds a flock on this file for as long as it runs. The kernel drops it
+// however we go, a crash included.
+pub(crate) fn lock_path() -> Option<PathBuf> {
+    env::var_os("XDG_RUNTIME_DIR")
+    .filter(|dir| !dir.is_empty())
+    .map(|dir| PathBuf::from(dir).join("attention.lock"))
+}
+
+// How long --replace gives the other instance to put DPMS back and exit.
+const REPLACE_TIMEOUT: time::Duration = time::Duration::from_secs(5);
+
+pub(crate) fn acquire(runner: &dyn CommandRunner, path: &Path, replace: bool) -> Result<File, AttentionError> {
+    let lock_error = |source| AttentionError::Lock { path: path.to_owned(), source };
+    let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path).map_err(lock_error)?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let pid = holder(&mut file);
+            let Some(pid) = pid.filter(|_| replace) else {
+                return Err(AttentionError::AlreadyRunning { pid });
+            };
+            info!("Asking the attention running as PID {} to exit..", pid);
+            run_command(runner, "kill", &["-TERM", &pid.to_string()])?;
+            wait_for_lock(&file, REPLACE_TIMEOUT).ok_or(AttentionError::AlreadyRunning { pid: Some(pid) })?;
+        }
+        Err(TryLockError::Error(source)) => return Err(lock_error(source))
+    }
+
+    // The PID is only there to tell the next instance who to ask.
+    file.set_len(0).and_then(|_| file.rewind()).and_then(|_| write!(file, "{}", process::id())).map_err(lock_error)?;
+    Ok(file)
+}
+
+fn holder(file: &mut File) -> Option<u32> {
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).ok()?;
+    contents.trim().parse().ok()
+}
+
+fn wait_for_lock(file: &File, timeout: time::Duration) -> Option<()> {
+    let started = Instant::now();
+    while started.elapsed() < timeout {
+        if file.try_lock().is_ok() {
+            return Some(());
+        }
+        thread::sleep(time::Duration::from_millis(100));
+    }
+    None
+}
+
+// --pidfile is for init scripts, the lock above is what actually keeps a
+// second instance out. One left by a process that's gone is taken over.
+pub(crate) fn write_pidfile(path: &Path) -> Result<(), AttentionError> {
+    if let Some(pid) = read_pidfile(path).filter(|&pid| pid != process::id() && is_alive(pid)) {
+        return Err(AttentionError::AlreadyRunning { pid: Some(pid) });
+    }
+    fs::write(path, format!("{}\n", process::id())).map_err(|source| AttentionError::Pidfile { path: path.to_owned(), source })
+}
+
+pub(crate) fn read_pidfile(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok().and_then(|contents| contents.trim().parse().ok())
+}
+
+pub(crate) fn remove_pidfile(state: &mut State) {
+    if let Some(path) = state.pidfile.take() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// Where a detached instance logs without --log-file, next to the socket
+// `attention stop` talks to it through.
+pub(crate) fn detached_log_path() -> Option<PathBuf> {
+    env::var_os("XDG_RUNTIME_DIR")
+    .filter(|dir| !dir.is_empty())
+    .map(|dir| PathBuf::from(dir).join("attention.log"))
+}
+
+pub(crate) fn is_detached() -> bool {
+    env::var_os(DETACHED).is_some()
+}
+
+// --detach starts us over with the same arguments under `setsid --fork`,
+// which leaves the terminal's session and forks once more, so closing the
+// terminal takes neither us nor the app down. The copy has no terminal to
+// write to, its stderr goes to the log, which catches the errors it exits
+// with too. We don't change directory, the paths given on the command line
+// stay relative to where they were typed.
+pub(crate) fn detach(log: Option<&Path>) -> Result<(), AttentionError> {
+    let stderr = match log {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path).map_err(|source| AttentionError::LogFile { path: path.to_owned(), source })?;
+            Stdio::from(file)
+        }
+        None => Stdio::null()
+    };
+    let spawn_error = |source| AttentionError::CommandSpawn { cmd: "setsid".to_owned(), source };
+    let exe = env::current_exe().map_err(spawn_error)?;
+    let status =
+    Command::new("setsid")
+    .arg("--fork")
+    .arg(exe)
+    .args(env::args_os().skip(1))
+    .env(DETACHED, "1")
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(stderr)
+    .status()
+    .map_err(spawn_error)?;
+    if !status.success() {
+        return Err(AttentionError::CommandFailed { cmd: "setsid".to_owned(), stderr: status.to_string() });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::MockRunner;
+
+    #[test]
+    fn a_second_instance_is_refused() {
+        let path = env::temp_dir().join(format!("attention-{}.lock", process::id()));
+        let runner = MockRunner::new();
+        let first = acquire(&runner, &path, false).unwrap();
+
+        // flock is per open file, so this conflicts even within one process.
+        let pid = Some(process::id());
+        assert!(matches!(acquire(&runner, &path, false), Err(AttentionError::AlreadyRunning { pid: held }) if held == pid));
+
+        drop(first);
+        assert!(acquire(&runner, &path, false).is_ok());
+        assert!(runner.calls().is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn a_stale_pidfile_is_taken_over_and_a_live_one_refused() {
+        let path = env::temp_dir().join(format!("attention-{}.pid", process::id()));
+        // PID 1 is always there, one past the kernel's limit never is.
+        fs::write(&path, "1\n").unwrap();
+        assert!(matches!(write_pidfile(&path), Err(AttentionError::AlreadyRunning { pid: Some(1) })));
+
+        fs::write(&path, "4194305\n").unwrap();
+        write_pidfile(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("{}\n", process::id()));
+
+        let mut state = State::new(crate::session::SessionType::X11);
+        state.pidfile = Some(path.clone());
+        remove_pidfile(&mut state);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,214 @@
+use core::time;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use log::{debug, warn};
+use serde::Serialize;
+#[cfg(unix)]
+use signal_hook::consts::SIGTERM;
+#[cfg(unix)]
+use signal_hook::low_level::raise;
+
+use crate::error::AttentionError;
+use crate::event::{report, Event};
+use crate::metrics::render;
+use crate::runner::CommandRunner;
+use crate::state::{lock, InhibitReason, ScreenBlankingState, State};
+use crate::tracking::{resume_inhibit, suspend_inhibit};
+
+// A running instance answers on this socket, one request per connection: a
+// line like `status` in, one JSON line back.
+pub(crate) fn socket_path() -> Option<PathBuf> {
+    env::var_os("XDG_RUNTIME_DIR")
+    .filter(|dir| !dir.is_empty())
+    .map(|dir| PathBuf::from(dir).join("attention.sock"))
+}
+
+#[derive(Serialize)]
+pub(crate) struct Status<'a> {
+    blanking: &'a ScreenBlankingState,
+    reasons: Vec<&'static str>,
+    paused: bool,
+    app: &'a str,
+    pid: Option<u32>
+}
+
+// While paused the trackers keep their reasons up to date without acting on
+// them, so resuming puts back whatever they want right now.
+pub(crate) fn set_paused(runner: &dyn CommandRunner, paused: bool, state: &mut State) -> Result<(), AttentionError> {
+    let app_name = state.app_name.clone();
+    if paused && !state.manual_override {
+        report(state.json, Event::Paused { app: &app_name });
+        state.manual_override = true;
+        suspend_inhibit(runner, state)?;
+    } else if !paused && state.manual_override {
+        report(state.json, Event::Resumed { app: &app_name });
+        state.manual_override = false;
+        resume_inhibit(runner, &app_name, state)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn respond(runner: &dyn CommandRunner, request: &str, state: &mut State) -> String {
+    let paused = match request.trim() {
+        "status" => None,
+        "pause" => Some(true),
+        "resume" => Some(false),
+        "toggle" => Some(!state.manual_override),
+        "metrics" => return render(state),
+        "stop" => return serde_json::json!({ "stopping": true }).to_string(),
+        // Enough HTTP for a scraper pointed at the socket.
+        request if request.starts_with("GET /metrics ") => return format!("HTTP/1.0 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\r\n{}", render(state)),
+        request => return serde_json::json!({ "error": format!("unknown request {}", request) }).to_string()
+    };
+    if let Some(paused) = paused
+        && let Err(err) = set_paused(runner, paused, state) {
+        return serde_json::json!({ "error": err.to_string() }).to_string();
+    }
+
+    // Every request answers with the status it left behind.
+    let mut reasons: Vec<&str> = state.inhibit_reasons.iter().copied().chain(state.reasons_elsewhere()).map(InhibitReason::as_str).collect();
+    reasons.sort_unstable();
+    reasons.dedup();
+    let status = Status {
+        blanking: &state.last_screen_blanking_state,
+        reasons,
+        paused: state.manual_override,
+        app: &state.app_name,
+        pid: state.window.as_ref().map(|window| window.pid)
+    };
+    serde_json::to_string(&status).unwrap_or_default()
+}
+
+// A socket left behind by a crashed instance doesn't answer and gets
+// replaced, one that does answer belongs to another instance and is left be.
+#[cfg(unix)]
+fn bind(path: &PathBuf) -> io::Result<UnixListener> {
+    match UnixListener::bind(path) {
+        Err(err) if err.kind() == io::ErrorKind::AddrInUse && UnixStream::connect(path).is_err() => {
+            fs::remove_file(path)?;
+            UnixListener::bind(path)
+        }
+        result => result
+    }
+}
+
+#[cfg(unix)]
+fn serve_one(mut stream: UnixStream, state: &Mutex<State>) -> io::Result<()> {
+    // A client that never sends its line shouldn't wedge the listener.
+    stream.set_read_timeout(Some(time::Duration::from_secs(1)))?;
+    let mut request = String::new();
+    BufReader::new(&stream).read_line(&mut request)?;
+    let mut state = lock(state);
+    let runner = state.runner.clone();
+    let response = respond(&runner, &request, &mut state);
+    drop(state);
+    writeln!(stream, "{}", response)?;
+    // The signal handler puts blanking back and cleans up the way it does
+    // for kill, the answer has to be out before it exits.
+    if request.trim() == "stop"
+        && let Err(err) = raise(SIGTERM) {
+        warn!("Couldn't stop: {}..", err);
+    }
+    Ok(())
+}
+
+// Not being able to listen only costs the status queries, so it's a warning.
+#[cfg(unix)]
+pub(crate) fn listen(state: Arc<Mutex<State>>) {
+    let Some(path) = socket_path() else {
+        warn!("XDG_RUNTIME_DIR isn't set, status queries are off..");
+        return;
+    };
+    let listener = match bind(&path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            warn!("Couldn't listen on {}: {}", path.display(), err);
+            return;
+        }
+    };
+    lock(&state).socket = Some(path);
+
+    thread::spawn(move || {
+        for stream in listener.incoming().filter_map(Result::ok) {
+            if let Err(err) = serve_one(stream, &state) {
+                debug!("Status client went away: {}", err);
+            }
+        }
+    });
+}
+
+// The socket is a unix one, elsewhere there's nothing to query.
+#[cfg(not(unix))]
+pub(crate) fn listen(_state: Arc<Mutex<State>>) {
+    warn!("Status queries need a unix socket, they're off..");
+}
+
+pub(crate) fn remove_socket(state: &mut State) {
+    if let Some(path) = state.socket.take() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// The client side for `attention status` and friends.
+#[cfg(unix)]
+pub(crate) fn send(request: &str) -> Result<String, AttentionError> {
+    let path = socket_path().ok_or(AttentionError::NotRunning)?;
+    let mut stream = UnixStream::connect(&path).map_err(|_| AttentionError::NotRunning)?;
+    let ipc_error = |source| AttentionError::Ipc { path: path.clone(), source };
+    writeln!(stream, "{}", request).map_err(ipc_error)?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).map_err(ipc_error)?;
+    Ok(response.trim_end().to_owned())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn send(_request: &str) -> Result<String, AttentionError> {
+    Err(AttentionError::NotRunning)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inhibit::turn_off_screen_blanking;
+    use crate::runner::MockRunner;
+    use crate::session::SessionType;
+
+    #[test]
+    fn status_reports_the_blanking_state_and_reasons() {
+        let mut state = State::new(SessionType::X11);
+        state.app_name = "mpv".to_owned();
+        state.last_screen_blanking_state = ScreenBlankingState::Off;
+        state.inhibit_reasons.insert(InhibitReason::Fullscreen);
+        state.inhibit_reasons.insert(InhibitReason::Audio);
+        let runner = MockRunner::new();
+        assert_eq!(respond(&runner, "status\n", &mut state), r#"{"blanking":"off","reasons":["audio","fullscreen"],"paused":false,"app":"mpv","pid":null}"#);
+        assert_eq!(respond(&runner, "reboot", &mut state), r#"{"error":"unknown request reboot"}"#);
+        assert_eq!(respond(&runner, "stop\n", &mut state), r#"{"stopping":true}"#);
+        assert!(respond(&runner, "metrics", &mut state).starts_with("# HELP attention_inhibit_active"));
+        assert!(respond(&runner, "GET /metrics HTTP/1.1\r\n", &mut state).starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(runner.calls().is_empty());
+    }
+
+    #[test]
+    fn pausing_lets_the_screen_blank_until_resumed() {
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        turn_off_screen_blanking(&runner, "mpv", InhibitReason::Audio, &mut state).unwrap();
+        respond(&runner, "toggle", &mut state);
+        assert!(state.manual_override && state.last_screen_blanking_state == ScreenBlankingState::On);
+
+        // Still wanted while paused, so resuming turns it straight back off.
+        turn_off_screen_blanking(&runner, "mpv", InhibitReason::Fullscreen, &mut state).unwrap();
+        respond(&runner, "resume", &mut state);
+        assert!(!state.manual_override && state.last_screen_blanking_state == ScreenBlankingState::Off);
+        assert_eq!(runner.calls(), vec!["xset -dpms", "xset +dpms", "xset -dpms"]);
+    }
+}
@@ -0,0 +1,799 @@
+use core::time;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process;
+#[cfg(not(unix))]
+use std::iter;
+#[cfg(not(unix))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use chrono::Local;
+use log::{debug, error, info, warn};
+use signal_hook::consts::{SIGINT, SIGTERM};
+#[cfg(unix)]
+use signal_hook::iterator::Signals;
+use zbus::blocking::Connection;
+
+mod audio;
+mod condition;
+mod config;
+mod debug;
+mod display;
+mod error;
+mod event;
+mod inhibit;
+mod inhibitor;
+mod instance;
+// Only served over a unix socket, elsewhere the answers have nowhere to go.
+#[cfg_attr(not(unix), allow(dead_code, unused_imports))]
+mod ipc;
+mod logfile;
+mod metrics;
+mod mpris;
+mod options;
+mod output;
+mod power;
+mod procfs;
+mod runner;
+mod saved;
+mod service;
+mod session;
+mod state;
+mod style;
+mod tracking;
+mod watch;
+mod window;
+
+pub use audio::{AudioBackend, AudioRole, StreamFilter};
+pub use condition::{Condition, Signal};
+pub use config::Config;
+pub use error::AttentionError;
+pub use event::Event;
+pub use logfile::LogFile;
+pub use metrics::Metrics;
+pub use inhibit::{BlankingGuard, DpmsSettings, InhibitMethod, InhibitScope, ScopeInhibits, Urgency};
+pub use inhibitor::Inhibitor;
+#[cfg(windows)]
+pub use inhibitor::ExecutionStateInhibitor;
+#[cfg(target_os = "macos")]
+pub use inhibitor::CaffeinateInhibitor;
+pub use options::{ActiveHours, Command, Options, Target};
+pub use runner::{CommandRunner, SystemRunner};
+pub use session::SessionType;
+pub use style::format_log;
+pub use state::{AppState, FullscreenState, InhibitReason, ScreenBlankingState, State, TrackAudioState};
+pub use window::{MatchBy, TrackedWindow, WindowMatch};
+
+use audio::detect_audio_backend;
+use debug::{dump_due, dump_window};
+use display::{display_gone, display_lost, wait_for_display};
+use event::report;
+use inhibit::{end_startup_grace, gnome_session_running, heartbeat, read_dpms_settings, release_screen_blanking, turn_off_screen_blanking, turn_on_screen_blanking};
+use instance::{acquire, is_detached, lock_path, read_pidfile, remove_pidfile, write_pidfile};
+use ipc::{listen, remove_socket, send};
+use metrics::CountingRunner;
+use power::on_ac_power;
+use procfs::is_alive;
+use runner::{in_path, run_command, with_retries};
+use saved::{load, remove_state_file, save, state_file};
+use service::{sd_notify, watchdog};
+use session::{check_dependencies, detect_session};
+use state::lock;
+use tracking::{audio_changed, focus_changed, fullscreen_changed, fullscreen_reading, we_are_tracking_audio, we_are_tracking_condition, we_are_tracking_fullscreen, we_are_tracking_hours, we_are_tracking_idle, we_are_tracking_power, we_are_tracking_mpris, Readers, Schedule, Wanted};
+use watch::Watcher;
+use window::{app_crashed, find_matching_window, find_tracked_window, find_window, is_window_closed, refresh_other_windows, window_closed, window_gone, launch_app, print_windows, process_name, stop_app, wait_for_window_to_reopen, wait_for_window_to_show_up};
+
+// Prints the open windows for `attention list`.
+pub fn list(runner: &dyn CommandRunner, with_class: bool) -> Result<(), AttentionError> {
+    print_windows(runner, detect_session(None)?, with_class)
+}
+
+// Runs every check once against the app's window and prints what each saw.
+pub fn debug(runner: &dyn CommandRunner, app_name: &str) -> Result<(), AttentionError> {
+    debug::debug(runner, detect_session(None)?, detect_audio_backend(), app_name)
+}
+
+// Sends `status`, `pause`, `resume` or `toggle` to the running instance and
+// prints the status it answers with.
+pub fn ask(request: &str) -> Result<(), AttentionError> {
+    println!("{}", send(request)?);
+    Ok(())
+}
+
+fn system_runner(options: &Options) -> SystemRunner {
+    SystemRunner { timeout: options.command_timeout, display: options.display.clone() }
+}
+
+// `attention stop` asks over the socket like the rest, --pidfile is for an
+// instance that can't be reached there.
+pub fn stop(runner: &dyn CommandRunner, pidfile: Option<&Path>) -> Result<(), AttentionError> {
+    let Some(path) = pidfile else {
+        return ask("stop");
+    };
+    let pid = read_pidfile(path).filter(|&pid| is_alive(pid)).ok_or(AttentionError::NotRunning)?;
+    run_command(runner, "kill", &["-TERM", &pid.to_string()])?;
+    Ok(())
+}
+
+// Where a detached instance logs when --log-file isn't given.
+pub fn detached_log_path() -> Option<PathBuf> {
+    instance::detached_log_path()
+}
+
+// Starts the copy that keeps going in the background for --detach, unless
+// this already is it. True when the caller is done and should exit.
+pub fn detach(options: &Options) -> Result<bool, AttentionError> {
+    if !options.detach || is_detached() {
+        return Ok(false);
+    }
+    instance::detach(options.log_file.as_deref())?;
+    match &options.log_file {
+        Some(path) => println!("Detached, logging to {}", path.display()),
+        None => println!("Detached, with nowhere to log to")
+    }
+    Ok(true)
+}
+
+// Writes the systemd unit for `attention install-service`.
+pub fn install_service(profile: &str) -> Result<(), AttentionError> {
+    service::install_service(profile)
+}
+
+// Launches or attaches to the app described by `options` and keeps the
+// screen awake while it's busy. The state sits behind a mutex so a signal
+// handler, or whoever embeds us, can restore blanking from another thread.
+pub struct Attention {
+    options: Options,
+    target: Target,
+    runner: Arc<dyn CommandRunner + Send + Sync>,
+    state: Arc<Mutex<State>>
+}
+
+impl Attention {
+    pub fn new(options: Options) -> Result<Self, AttentionError> {
+        let runner = system_runner(&options);
+        Self::with_runner(options, Box::new(runner))
+    }
+
+    pub fn with_runner(options: Options, runner: Box<dyn CommandRunner + Send + Sync>) -> Result<Self, AttentionError> {
+        let target = options.target()?;
+        #[cfg(windows)]
+        let inhibitor: Option<Box<dyn Inhibitor + Send>> = Some(Box::new(ExecutionStateInhibitor));
+        #[cfg(target_os = "macos")]
+        let inhibitor: Option<Box<dyn Inhibitor + Send>> = Some(Box::new(CaffeinateInhibitor::default()));
+        #[cfg(not(any(windows, target_os = "macos")))]
+        let inhibitor: Option<Box<dyn Inhibitor + Send>> = None;
+        // With no window to look for a timed run can do without a session,
+        // the platform's inhibitor keeps the display on by itself. X11 only
+        // stands in for the session there, nothing asks it anything.
+        let session = match detect_session(options.display.as_deref()) {
+            Err(AttentionError::NoSession) if inhibitor.is_some() && options.timed() => SessionType::X11,
+            session => session?
+        };
+        let mut state = State::new(session);
+        state.inhibitor = inhibitor;
+        state.runner = system_runner(&options);
+        // Hyprland has no xset to speak of, hypridle honours the D-Bus inhibit.
+        state.inhibit_method = match (options.inhibit_method, state.session) {
+            (InhibitMethod::Xset, SessionType::Hyprland) => InhibitMethod::Dbus,
+            (method, _) => method
+        };
+        state.json = options.json;
+        state.dry_run = options.dry_run;
+        state.notify = !options.no_notify;
+        state.kill_on_exit = options.kill_on_exit;
+        state.inhibit_scope = options.inhibit;
+        state.fullscreen_confirm = options.fullscreen_confirm;
+        state.debug_dump = options.debug_dump;
+        state.output = match (&options.output, state.session) {
+            (Some(_), SessionType::Sway | SessionType::Hyprland) => {
+                warn!("--output needs xrandr, fullscreen counts on every output..");
+                None
+            }
+            (output, _) => output.clone()
+        };
+        state.startup_grace = options.startup_grace;
+        state.warmup_ignore_audio = options.warmup_ignore_audio;
+        state.window_gone_grace = options.window_gone_grace;
+        state.notify_on_text = options.notify_on_text.clone();
+        state.reason = options.reason.clone();
+        state.notify_off_text = options.notify_off_text.clone();
+        state.notify_icon = options.notify_icon.clone();
+        state.notify_urgency = options.notify_urgency;
+        state.notify_cooldown = options.notify_cooldown;
+        state.notify_timeout = options.notify_timeout;
+        state.notify_replace = options.notify_replace;
+        state.on_inhibit = options.on_inhibit.clone();
+        state.on_release = options.on_release.clone();
+        state.stream_filter = StreamFilter { props: options.audio_match_prop.clone(), role: options.audio_role };
+        state.audio_backend = match options.audio_backend {
+            AudioBackend::Auto => detect_audio_backend(),
+            backend => backend
+        };
+        // Notifications are a nicety the platform inhibitor can go without.
+        if state.inhibitor.is_some() && !in_path("notify-send") {
+            state.notify = false;
+        }
+        check_dependencies(&options, &state)?;
+        // Before reading DPMS, a replaced instance puts back what it turned off.
+        if !options.dry_run {
+            match lock_path() {
+                Some(path) => state.instance_lock = Some(acquire(&*runner, &path, options.replace)?),
+                None => warn!("XDG_RUNTIME_DIR isn't set, another instance could fight over DPMS..")
+            }
+        }
+        match state.inhibit_method {
+            // The inhibit method is never used then, so nothing is read or connected for it.
+            _ if state.inhibitor.is_some() => {}
+            InhibitMethod::Dbus | InhibitMethod::Gnome => state.dbus = Some(Connection::session().map_err(AttentionError::Dbus)?),
+            // XWayland takes xset without it reaching the outputs.
+            InhibitMethod::Xset if state.session == SessionType::X11 && env::var_os("WAYLAND_DISPLAY").is_some() => match Connection::session() {
+                Ok(connection) if gnome_session_running(&connection) => {
+                    state.inhibit_method = InhibitMethod::Gnome;
+                    state.dbus = Some(connection);
+                }
+                _ => state.dpms = read_dpms_settings(&*runner)?
+            },
+            InhibitMethod::Logind => state.dbus = Some(Connection::system().map_err(AttentionError::Dbus)?),
+            InhibitMethod::Xset if state.session == SessionType::X11 => state.dpms = read_dpms_settings(&*runner)?,
+            InhibitMethod::Xset | InhibitMethod::XdgScreensaver | InhibitMethod::Reset => {}
+        }
+        let runner = Arc::new(CountingRunner { runner, failures: Arc::clone(&state.metrics.command_failures) });
+        // --once needs to know what the last run left behind to undo it.
+        if options.restore_state || options.once {
+            restore_state(&*runner, options.once, &mut state)?;
+        }
+        if let Some(path) = &options.pidfile {
+            write_pidfile(path)?;
+            state.pidfile = Some(path.clone());
+        }
+
+        Ok(Self { options, target, runner, state: Arc::new(Mutex::new(state)) })
+    }
+
+    pub fn state(&self) -> Arc<Mutex<State>> {
+        Arc::clone(&self.state)
+    }
+
+    // Restores screen blanking when we get killed, otherwise `xset -dpms` would
+    // outlive us. Only the first signal is acted on, any signal after it is
+    // swallowed so a second Ctrl+C can't cut the restore short.
+    pub fn restore_on_signal(&self) -> Result<(), AttentionError> {
+        let mut signals = Signals::new([SIGINT, SIGTERM])
+        .map_err(AttentionError::SignalHandler)?;
+        let state = self.state();
+
+        thread::spawn(move || {
+            if let Some(signal) = signals.forever().next() {
+                let mut state = lock(&state);
+                let runner = state.runner.clone();
+                report(state.json, Event::Signal { signal });
+                if let Err(err) = turn_on_screen_blanking(&runner, &mut state) {
+                    error!("{}", err);
+                }
+                stop_app(&runner, &mut state);
+                remove_socket(&mut state);
+                remove_pidfile(&mut state);
+                remove_state_file(&mut state);
+                report(state.json, Event::ShuttingDown);
+                process::exit(128 + signal);
+            }
+        });
+        Ok(())
+    }
+
+    // Answers `attention status` from another thread while tracking.
+    pub fn listen(&self) {
+        listen(self.state());
+    }
+
+    // Runs until the tracked window closes, or all of them with --apps.
+    pub fn track(&mut self) -> Result<(), AttentionError> {
+        match &self.target {
+            Target::AttachApps(names) => return self.track_apps(names),
+            &Target::Timed(duration) => return self.keep_awake_for(duration),
+            _ => {}
+        }
+        let (app_name, window) = resolve_window(&*self.runner, &self.options, &self.target, &self.state)?;
+        {
+            let mut state = lock(&self.state);
+            state.window = Some(window.clone());
+            state.window_shown();
+            state.app_name = app_name.clone();
+            if self.options.inhibit_on_start {
+                turn_off_screen_blanking(&*self.runner, &app_name, InhibitReason::Start, &mut state)?;
+            }
+        }
+        sd_notify("READY=1");
+        let mut restarts = 0;
+        loop {
+            match self.track_window(&app_name) {
+                Err(err) if self.reconnects(&err) => self.reconnect()?,
+                Err(AttentionError::AppExited { .. }) if self.options.max_restarts.is_none_or(|max| restarts < max) => {
+                    restarts += 1;
+                    self.restart_app(&app_name, restarts)?;
+                }
+                result => return result
+            }
+        }
+    }
+
+    // Waits a second before the first restart, doubling up to 16s for
+    // an app that keeps dying.
+    fn restart_app(&self, app_name: &str, attempt: u32) -> Result<(), AttentionError> {
+        report(lock(&self.state).json, Event::AppRestarting { app: app_name, attempt });
+        thread::sleep(time::Duration::from_secs(1 << (attempt - 1).min(4)));
+        let (_, window) = resolve_window(&*self.runner, &self.options, &Target::Launch, &self.state)?;
+        let mut state = lock(&self.state);
+        state.window = Some(window);
+        state.window_shown();
+        Ok(())
+    }
+
+    fn track_window(&self, app_name: &str) -> Result<(), AttentionError> {
+        let runner = &*self.runner;
+        let options = &self.options;
+        let (session, window) = {
+            let state = lock(&self.state);
+            (state.session, state.window.clone().ok_or_else(|| AttentionError::NoMatchingWindow { what: format!("{:?}", app_name) })?)
+        };
+
+        // Only X11 has a way to be told about fullscreen changes and only
+        // the subscribe backend about audio ones, the rest is polled.
+        let mut watcher = Watcher::new(options.display.clone());
+        let mut spied = None;
+        if options.track_fullscreen && session == SessionType::X11 {
+            spied = watcher.spy_on_fullscreen(&window.id);
+        }
+        if options.track_audio && lock(&self.state).audio_backend == AudioBackend::Subscribe {
+            watcher.subscribe_to_audio();
+        }
+        let mut audio_dirty = true;
+        let mut playing = false;
+
+        let mut readers = {
+            let state = lock(&self.state);
+            Readers::new(Arc::clone(&self.runner), session, state.audio_backend, state.stream_filter.clone(), options.max_retries)
+        };
+        let mut window = window;
+        let mut audio_schedule = Schedule::new(options.audio_interval.unwrap_or(options.interval));
+        let mut fullscreen_schedule = Schedule::new(options.fullscreen_interval.unwrap_or(options.interval));
+        loop {
+            if options.track_fullscreen || options.condition.is_some() {
+                refresh_other_windows(runner, &mut window, &mut lock(&self.state))?;
+            }
+            // The spy only sees the one window, the others are polled.
+            let polls_audio = options.track_audio && !watcher.subscribed();
+            let polls_fullscreen = options.track_fullscreen && (!watcher.spying() || !window.others.is_empty());
+            let now = Instant::now();
+            let wanted = Wanted {
+                audio: (polls_audio && audio_schedule.due(now)) || (options.track_audio && audio_dirty),
+                fullscreen: polls_fullscreen && fullscreen_schedule.due(now),
+                focus: options.focused_only
+            };
+            let readings = readers.take(&window, wanted, now + options.interval);
+            // No answer in time, it was open the last time we heard.
+            let open = readings.open.transpose()?.unwrap_or(true);
+            if open {
+                lock(&self.state).window_gone_since = None;
+            } else if !window_gone(window.pid, &mut lock(&self.state)) {
+                thread::sleep(options.interval);
+                continue;
+            }
+            if !open {
+                window_closed(runner, app_name, &mut lock(&self.state))?;
+                let reopened = if options.persist {
+                    wait_for_window_to_reopen(runner, session, &window, options.interval, &self.state)?
+                } else {
+                    None
+                };
+                let mut state = lock(&self.state);
+                let Some(reopened) = reopened else {
+                    if options.restart_app && let Some(status) = app_crashed(&mut state) {
+                        return Err(AttentionError::AppExited { app_name: app_name.to_owned(), status });
+                    }
+                    report(state.json, Event::ShuttingDown);
+                    return Ok(());
+                };
+
+                report(state.json, Event::WindowReopened { app: app_name });
+                window = reopened;
+                readers.forget();
+                state.window = Some(window.clone());
+                state.window_shown();
+                if options.track_fullscreen && session == SessionType::X11 {
+                    spied = watcher.spy_on_fullscreen(&window.id);
+                }
+                // The readings were about the old window, take them again.
+                audio_dirty = true;
+                fullscreen_schedule.reset();
+                continue;
+            }
+
+            {
+                let mut state = lock(&self.state);
+                run_gates(runner, options, app_name, readings.focused.transpose()?, &mut state)?;
+                if let Some(condition) = &options.condition {
+                    we_are_tracking_condition(runner, app_name, condition, &window, &mut state)?;
+                }
+                if options.track_audio {
+                    // Subscribed, the last answer stands until pactl says
+                    // something changed.
+                    if let Some(reading) = readings.playing {
+                        playing = reading?;
+                    }
+                    audio_changed(runner, app_name, playing, options.grace, &mut state)?;
+                }
+                if options.track_mpris {
+                    we_are_tracking_mpris(runner, app_name, window.pid, &mut state)?;
+                }
+                // The spy dies with a stale id, polling finds the new one.
+                let previous_id = window.id.clone();
+                let polled = match readings.fullscreen {
+                    Some(reading) => fullscreen_reading(runner, reading, &mut window, &mut state)?,
+                    None => None
+                };
+                if window.id != previous_id && session == SessionType::X11 {
+                    spied = watcher.spy_on_fullscreen(&window.id);
+                }
+                if let Some(fullscreen) = polled.or(spied) {
+                    fullscreen_changed(runner, app_name, fullscreen, &mut state)?;
+                }
+                end_first_look(runner, &mut state)?;
+                end_startup_grace(runner, &mut state)?;
+                heartbeat(runner, &state)?;
+                watchdog();
+                if dump_due(&mut state) {
+                    dump_window(runner, &state);
+                }
+                if options.once {
+                    finish_once(&mut state);
+                    return Ok(());
+                }
+            }
+
+            let now = Instant::now();
+            let mut sleep = options.interval;
+            if polls_audio {
+                sleep = sleep.min(audio_schedule.until(now));
+            }
+            if polls_fullscreen {
+                sleep = sleep.min(fullscreen_schedule.until(now));
+            }
+            let changes = watcher.wait(sleep);
+            // The last spied state stands until xprop says otherwise, it
+            // still counts once per loop towards --fullscreen-confirm.
+            spied = changes.fullscreen.or(spied);
+            audio_dirty = changes.audio;
+        }
+    }
+
+    // Every app's window is polled in turn, the screen stays on while any of
+    // them wants it. The watchers only know about one window, so they sit
+    // this one out.
+    fn track_apps(&self, names: &[String]) -> Result<(), AttentionError> {
+        loop {
+            match self.track_apps_windows(names) {
+                // Each window is looked up again, by title as the first time.
+                Err(err) if self.reconnects(&err) => {
+                    display_lost(&*self.runner, &mut lock(&self.state));
+                    wait_for_display(&*self.runner, self.options.reconnect_timeout, &self.state)?;
+                }
+                result => return result
+            }
+        }
+    }
+
+    fn track_apps_windows(&self, names: &[String]) -> Result<(), AttentionError> {
+        let runner = &*self.runner;
+        let options = &self.options;
+        let session = lock(&self.state).session;
+        let mut apps = Vec::new();
+        for name in names {
+            let matching = WindowMatch::Title(name.clone());
+            let (id, pid) = find_matching_window(runner, session, &matching)?
+            .ok_or_else(|| AttentionError::NoMatchingWindow { what: format!("the title {:?}", name) })?;
+            apps.push(AppState::new(name.clone(), TrackedWindow { id, pid, matching, by: options.match_by, others: Vec::new() }));
+        }
+        let everyone = names.join(", ");
+        {
+            let mut state = lock(&self.state);
+            if state.inhibit_reasons.contains(&InhibitReason::Restored) {
+                apps[0].inhibit_reasons.insert(InhibitReason::Restored);
+            }
+            state.track_apps(apps);
+            state.window_shown();
+            if options.inhibit_on_start {
+                turn_off_screen_blanking(runner, &everyone, InhibitReason::Start, &mut state)?;
+            }
+        }
+        sd_notify("READY=1");
+
+        let mut audio_schedule = Schedule::new(options.audio_interval.unwrap_or(options.interval));
+        let mut fullscreen_schedule = Schedule::new(options.fullscreen_interval.unwrap_or(options.interval));
+        let mut open = vec![true; names.len()];
+        loop {
+            for (index, name) in names.iter().enumerate() {
+                if !open[index] {
+                    continue;
+                }
+                open[index] = !with_retries(options.max_retries, || {
+                    let mut state = lock(&self.state);
+                    state.switch_app(index);
+                    let Some(window) = state.window.clone() else {
+                        return Ok(true);
+                    };
+                    is_window_closed(runner, name, &window, &mut state)
+                })?;
+            }
+            if !open.contains(&true) {
+                report(lock(&self.state).json, Event::ShuttingDown);
+                return Ok(());
+            }
+
+            {
+                let mut state = lock(&self.state);
+                run_gates(runner, options, &everyone, None, &mut state)?;
+                let dump = dump_due(&mut state);
+                let now = Instant::now();
+                let (audio_due, fullscreen_due) = (audio_schedule.due(now), fullscreen_schedule.due(now));
+                for index in (0..names.len()).filter(|&index| open[index]) {
+                    state.switch_app(index);
+                    let (Some(mut window), app_name) = (state.window.clone(), state.app_name.clone()) else {
+                        continue;
+                    };
+                    if options.track_fullscreen || options.condition.is_some() {
+                        refresh_other_windows(runner, &mut window, &mut state)?;
+                    }
+                    if let Some(condition) = &options.condition {
+                        we_are_tracking_condition(runner, &app_name, condition, &window, &mut state)?;
+                    }
+                    if options.track_audio && audio_due {
+                        we_are_tracking_audio(runner, &app_name, window.pid, options.grace, &mut state)?;
+                    }
+                    if options.track_mpris {
+                        we_are_tracking_mpris(runner, &app_name, window.pid, &mut state)?;
+                    }
+                    if options.track_fullscreen && fullscreen_due {
+                        we_are_tracking_fullscreen(runner, &app_name, &mut window, &mut state)?;
+                    }
+                    end_first_look(runner, &mut state)?;
+                    if dump {
+                        dump_window(runner, &state);
+                    }
+                }
+                end_startup_grace(runner, &mut state)?;
+                heartbeat(runner, &state)?;
+                watchdog();
+                if options.once {
+                    finish_once(&mut state);
+                    return Ok(());
+                }
+            }
+            let now = Instant::now();
+            let mut sleep = options.interval;
+            if options.track_audio {
+                sleep = sleep.min(audio_schedule.until(now));
+            }
+            if options.track_fullscreen {
+                sleep = sleep.min(fullscreen_schedule.until(now));
+            }
+            thread::sleep(sleep);
+        }
+    }
+
+    // Only X11 tools say the display is gone, a Wayland compositor going away
+    // takes our session with it.
+    fn reconnects(&self, err: &AttentionError) -> bool {
+        !self.options.no_reconnect && lock(&self.state).session == SessionType::X11 && display_gone(err)
+    }
+
+    // Window ids don't outlive the server, the window is looked up again by
+    // its pid. If it's not there the loop finds it closed as usual.
+    fn reconnect(&self) -> Result<(), AttentionError> {
+        let runner = &*self.runner;
+        display_lost(runner, &mut lock(&self.state));
+        wait_for_display(runner, self.options.reconnect_timeout, &self.state)?;
+        let mut state = lock(&self.state);
+        let session = state.session;
+        if let Some(window) = state.window.as_mut()
+            && let Some(id) = find_tracked_window(runner, session, window)? {
+            window.id = id;
+        }
+        state.window_shown();
+        Ok(())
+    }
+
+    // `--for` and `--until`, nothing to watch, only the time to wait out. A
+    // pause over the socket still lets the screen blank in between.
+    fn keep_awake_for(&mut self, duration: time::Duration) -> Result<(), AttentionError> {
+        // Worked out before anything is inhibited, a duration past what
+        // Instant can hold would otherwise leave DPMS off.
+        let until = Instant::now().checked_add(duration).ok_or(AttentionError::DurationTooLong { duration })?;
+        info!("Keeping the screen on for {}s..", duration.as_secs());
+        lock(&self.state).app_name = self.options.app_name.clone().unwrap_or("attention".to_owned());
+        self.inhibit()?;
+        sd_notify("READY=1");
+        // In steps, so the watchdog is still fed through a long wait.
+        while let Some(left) = until.checked_duration_since(Instant::now()).filter(|left| !left.is_zero()) {
+            watchdog();
+            thread::sleep(left.min(time::Duration::from_secs(1)));
+        }
+        self.release()
+    }
+
+    pub fn inhibiting(&self) -> bool {
+        lock(&self.state).last_screen_blanking_state == ScreenBlankingState::Off
+    }
+
+    // Keeps the screen awake regardless of what the app is doing, until
+    // `release` is called.
+    pub fn inhibit(&mut self) -> Result<(), AttentionError> {
+        let mut state = lock(&self.state);
+        let app_name = if state.app_name.is_empty() {
+            self.options.app_name.clone().unwrap_or_default()
+        } else {
+            state.app_name.clone()
+        };
+        turn_off_screen_blanking(&*self.runner, &app_name, InhibitReason::Manual, &mut state)
+    }
+
+    // Gives blanking back no matter who asked for it to be off.
+    pub fn release(&mut self) -> Result<(), AttentionError> {
+        turn_on_screen_blanking(&*self.runner, &mut lock(&self.state))
+    }
+}
+
+// signal-hook's iterator is unix only, elsewhere a caught signal is only
+// flagged and the flag is looked at every so often.
+#[cfg(not(unix))]
+struct Signals(Arc<AtomicUsize>);
+
+#[cfg(not(unix))]
+impl Signals {
+    fn new(signals: [i32; 2]) -> std::io::Result<Self> {
+        let caught = Arc::new(AtomicUsize::new(0));
+        for signal in signals {
+            signal_hook::flag::register_usize(signal, Arc::clone(&caught), signal as usize)?;
+        }
+        Ok(Self(caught))
+    }
+
+    fn forever(&mut self) -> impl Iterator<Item = i32> + '_ {
+        iter::from_fn(|| loop {
+            match self.0.swap(0, Ordering::SeqCst) {
+                0 => thread::sleep(time::Duration::from_millis(100)),
+                signal => return Some(signal as i32)
+            }
+        })
+    }
+}
+
+// --once leaves blanking the way its one look wanted and says which.
+fn finish_once(state: &mut State) {
+    state.keep_on_exit = true;
+    let mut reasons: Vec<&str> = state.inhibit_reasons.iter().copied().chain(state.reasons_elsewhere()).map(InhibitReason::as_str).collect();
+    reasons.sort_unstable();
+    reasons.dedup();
+    if state.last_screen_blanking_state == ScreenBlankingState::Off {
+        println!("inhibiting: {}", reasons.join(","));
+    } else {
+        println!("not inhibiting");
+    }
+}
+
+// Blanking the last instance left off is turned off again right away, the
+// trackers get to drop it after their first look. With `adopt` it's still
+// off, from the last --once, and only taken over.
+fn restore_state(runner: &dyn CommandRunner, adopt: bool, state: &mut State) -> Result<(), AttentionError> {
+    let Some(path) = state_file() else {
+        warn!("XDG_RUNTIME_DIR isn't set, there's nowhere to keep the state..");
+        return Ok(());
+    };
+    let previous = load(&path);
+    state.state_file = Some(path);
+    match previous {
+        Some(previous) if previous.blanking == ScreenBlankingState::Off => {
+            state.dpms = previous.dpms.or(state.dpms);
+            state.app_name = previous.app;
+            if adopt {
+                state.last_screen_blanking_state = ScreenBlankingState::Off;
+                state.metrics.inhibited();
+                state.inhibit_reasons.insert(InhibitReason::Restored);
+                return Ok(());
+            }
+            let app_name = state.app_name.clone();
+            turn_off_screen_blanking(runner, &app_name, InhibitReason::Restored, state)
+        }
+        _ => {
+            save(state);
+            Ok(())
+        }
+    }
+}
+
+// What was held off before the trackers had their first look is theirs to
+// keep on once they have.
+fn end_first_look(runner: &dyn CommandRunner, state: &mut State) -> Result<(), AttentionError> {
+    for reason in [InhibitReason::Restored, InhibitReason::Start] {
+        if state.inhibit_reasons.contains(&reason) {
+            release_screen_blanking(runner, reason, state)?;
+        }
+    }
+    Ok(())
+}
+
+// Focus, idleness, the hours and power gate the trackers, so they go first.
+// `focused` comes from the readings, None when focus isn't tracked.
+fn run_gates(runner: &dyn CommandRunner, options: &Options, app_name: &str, focused: Option<bool>, state: &mut State) -> Result<(), AttentionError> {
+    if let Some(focused) = focused {
+        focus_changed(runner, app_name, focused, options.grace, state)?;
+    }
+    if let Some(threshold) = options.idle_release {
+        we_are_tracking_idle(runner, app_name, threshold, state)?;
+    }
+    if let Some(hours) = options.active_hours {
+        we_are_tracking_hours(runner, app_name, hours, Local::now().time(), state)?;
+    }
+    if options.ac_only {
+        we_are_tracking_power(runner, app_name, on_ac_power(), state)?;
+    }
+    Ok(())
+}
+
+fn resolve_window(runner: &dyn CommandRunner, options: &Options, target: &Target, state: &Mutex<State>) -> Result<(String, TrackedWindow), AttentionError> {
+    let (session, json) = {
+        let state = lock(state);
+        (state.session, state.json)
+    };
+    match target {
+        Target::Launch => {
+            let app_name = options.app_name.clone().unwrap_or_default();
+            let matching = options.match_class.clone().map_or(WindowMatch::Title(app_name.clone()), WindowMatch::Class);
+            let child = launch_app(&app_name, &options.app_args, options.shell, options.display.as_deref(), &options.env, options.cwd.as_deref(), options.app_log.as_deref())?;
+            let pid = child.id();
+            lock(state).child = Some(child);
+            let window = wait_for_window_to_show_up(runner, session, &app_name, &matching, pid, state, options)?;
+            debug!("Found window {} \"{}\" of PID {}, class {}..", window.id, window.title, window.pid, window.class.as_deref().unwrap_or("unknown"));
+            if window.pid != pid {
+                report(json, Event::WindowAdopted { app: &app_name, pid: window.pid });
+            }
+            Ok((app_name, TrackedWindow { id: window.id, pid: window.pid, matching, by: options.match_by, others: Vec::new() }))
+        }
+        &Target::AttachPid(pid) => {
+            let app_name = options.app_name.clone().or_else(|| process_name(pid)).unwrap_or(pid.to_string());
+            let matching = options.match_class.clone().map_or(WindowMatch::Title(String::new()), WindowMatch::Class);
+            let id = find_window(runner, session, &matching, pid)?
+            .ok_or_else(|| AttentionError::NoMatchingWindow { what: format!("PID {}", pid) })?;
+            Ok((app_name, TrackedWindow { id, pid, matching, by: options.match_by, others: Vec::new() }))
+        }
+        Target::AttachTitle(title) => {
+            let matching = WindowMatch::Title(title.clone());
+            let (id, pid) = find_matching_window(runner, session, &matching)?
+            .ok_or_else(|| AttentionError::NoMatchingWindow { what: format!("the title {:?}", title) })?;
+            let app_name = options.app_name.clone().or_else(|| process_name(pid)).unwrap_or(title.clone());
+            Ok((app_name, TrackedWindow { id, pid, matching, by: options.match_by, others: Vec::new() }))
+        }
+        Target::AttachRegex(regex) => {
+            let matching = WindowMatch::Regex(regex.clone());
+            let (id, pid) = find_matching_window(runner, session, &matching)?
+            .ok_or_else(|| AttentionError::NoMatchingWindow { what: format!("the regex {}", regex) })?;
+            let app_name = process_name(pid).unwrap_or(regex.to_string());
+            Ok((app_name, TrackedWindow { id, pid, matching, by: options.match_by, others: Vec::new() }))
+        }
+        Target::AttachWindow(id) => {
+            let matching = WindowMatch::Id(id.clone());
+            let (id, pid) = find_matching_window(runner, session, &matching)?
+            .ok_or_else(|| AttentionError::NoMatchingWindow { what: format!("the id {}", id) })?;
+            let app_name = options.app_name.clone().or_else(|| process_name(pid)).unwrap_or(id.clone());
+            Ok((app_name, TrackedWindow { id, pid, matching, by: options.match_by, others: Vec::new() }))
+        }
+        // Looked up one by one in `track_apps`.
+        Target::AttachApps(names) => Err(AttentionError::NoMatchingWindow { what: format!("any of {}", names.join(", ")) }),
+        // Never looks for a window.
+        Target::Timed(_) => Err(AttentionError::NoMatchingWindow { what: "--for or --until, which don't track one".to_owned() })
+    }
+}
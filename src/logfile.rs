@@ -0,0 +1,77 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::AttentionError;
+use crate::style::plain_text;
+
+// What --log-file writes to. Once a line would take it past `max_size` the
+// file is moved to `<path>.1`, replacing the last one, and started over.
+pub struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64
+}
+
+impl LogFile {
+    pub fn open(path: &Path, max_size: u64) -> Result<Self, AttentionError> {
+        let file = append_to(path).map_err(|source| AttentionError::LogFile { path: path.to_owned(), source })?;
+        let size = file.metadata().map_or(0, |metadata| metadata.len());
+        // Nobody reads the file on a terminal.
+        plain_text();
+        Ok(Self { path: path.to_owned(), file, size, max_size })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let mut backup = self.path.clone().into_os_string();
+        backup.push(".1");
+        fs::rename(&self.path, backup)?;
+        self.file = append_to(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn append_to(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl Write for LogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use super::*;
+
+    #[test]
+    fn a_full_log_is_moved_aside_and_started_over() {
+        let path = env::temp_dir().join(format!("attention-{}.log", process::id()));
+        let backup = path.with_extension("log.1");
+        let _ = fs::remove_file(&path);
+
+        let mut log = LogFile::open(&path, 40).unwrap();
+        writeln!(log, "12:00:00 Turning off screen blanking..").unwrap();
+        writeln!(log, "12:00:09 Turning on screen blanking..").unwrap();
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "12:00:00 Turning off screen blanking..\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "12:00:09 Turning on screen blanking..\n");
+
+        let _ = fs::remove_file(path);
+        let _ = fs::remove_file(backup);
+    }
+}
@@ -0,0 +1,110 @@
+use core::time;
+use std::collections::BTreeMap;
+use std::io;
+use std::process::Output;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+use crate::runner::CommandRunner;
+use crate::state::{ScreenBlankingState, State};
+
+// What `metrics` on the socket answers with. Failures are counted by the
+// runner, which is shared with other threads, so they sit behind their own
+// lock.
+#[derive(Default)]
+pub struct Metrics {
+    pub inhibit_transitions: u64,
+    pub inhibited_for: time::Duration,
+    pub inhibited_since: Option<Instant>,
+    pub command_failures: Arc<Mutex<BTreeMap<String, u64>>>
+}
+
+impl Metrics {
+    pub(crate) fn inhibited(&mut self) {
+        self.inhibit_transitions += 1;
+        self.inhibited_since = Some(Instant::now());
+    }
+
+    pub(crate) fn released(&mut self) {
+        self.inhibit_transitions += 1;
+        if let Some(since) = self.inhibited_since.take() {
+            self.inhibited_for += since.elapsed();
+        }
+    }
+
+    fn seconds_inhibited(&self) -> f64 {
+        (self.inhibited_for + self.inhibited_since.map_or(time::Duration::ZERO, |since| since.elapsed())).as_secs_f64()
+    }
+}
+
+// Every command that can't be spawned or exits non-zero counts against its
+// name, whoever asked for it.
+pub(crate) struct CountingRunner {
+    pub runner: Box<dyn CommandRunner + Send + Sync>,
+    pub failures: Arc<Mutex<BTreeMap<String, u64>>>
+}
+
+impl CommandRunner for CountingRunner {
+    fn run(&self, cmd: &str, args: &[&str]) -> io::Result<Output> {
+        let result = self.runner.run(cmd, args);
+        if !matches!(&result, Ok(output) if output.status.success()) {
+            *self.failures.lock().unwrap_or_else(PoisonError::into_inner).entry(cmd.to_owned()).or_default() += 1;
+        }
+        result
+    }
+}
+
+// Prometheus' text format, scraped through the socket with something like
+// socat or asked for with `attention metrics`.
+#[cfg_attr(not(unix), allow(dead_code))]
+pub(crate) fn render(state: &State) -> String {
+    let metrics = &state.metrics;
+    let active = u8::from(state.last_screen_blanking_state == ScreenBlankingState::Off);
+    let mut lines = vec![
+        "# HELP attention_inhibit_active Whether screen blanking is held off right now.".to_owned(),
+        "# TYPE attention_inhibit_active gauge".to_owned(),
+        format!("attention_inhibit_active {}", active),
+        "# HELP attention_inhibit_transitions_total Times screen blanking was turned off or back on.".to_owned(),
+        "# TYPE attention_inhibit_transitions_total counter".to_owned(),
+        format!("attention_inhibit_transitions_total {}", metrics.inhibit_transitions),
+        "# HELP attention_inhibit_seconds_total Seconds screen blanking has been held off.".to_owned(),
+        "# TYPE attention_inhibit_seconds_total counter".to_owned(),
+        format!("attention_inhibit_seconds_total {:.3}", metrics.seconds_inhibited()),
+        "# HELP attention_command_failures_total External commands that failed or couldn't run.".to_owned(),
+        "# TYPE attention_command_failures_total counter".to_owned()
+    ];
+    let failures = metrics.command_failures.lock().unwrap_or_else(PoisonError::into_inner);
+    lines.extend(failures.iter().map(|(cmd, count)| format!("attention_command_failures_total{{cmd=\"{}\"}} {}", cmd.replace('\\', "\\\\").replace('"', "\\\""), count)));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inhibit::{turn_off_screen_blanking, turn_on_screen_blanking};
+    use crate::runner::MockRunner;
+    use crate::session::SessionType;
+    use crate::state::InhibitReason;
+
+    #[test]
+    fn transitions_and_failures_are_counted() {
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        let runner = CountingRunner {
+            runner: Box::new(MockRunner::new().failing("wmctrl", "Cannot open display.")),
+            failures: Arc::clone(&state.metrics.command_failures)
+        };
+        turn_off_screen_blanking(&runner, "mpv", InhibitReason::Audio, &mut state).unwrap();
+        let _ = runner.run("wmctrl", &["-lp"]);
+        let metrics = render(&state);
+        assert!(metrics.contains("\nattention_inhibit_active 1\n"));
+        assert!(metrics.contains("\nattention_inhibit_transitions_total 1\n"));
+        assert!(metrics.ends_with("\nattention_command_failures_total{cmd=\"wmctrl\"} 1"));
+
+        turn_on_screen_blanking(&runner, &mut state).unwrap();
+        let metrics = render(&state);
+        assert!(metrics.contains("\nattention_inhibit_active 0\n"));
+        assert!(metrics.contains("\nattention_inhibit_transitions_total 2\n"));
+        assert!(state.metrics.inhibited_since.is_none());
+    }
+}
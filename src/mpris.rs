@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+
+use log::debug;
+use zbus::blocking::Connection;
+use zbus::zvariant::OwnedValue;
+
+use crate::error::AttentionError;
+use crate::state::State;
+
+const PLAYER_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+// A player is the app's when the one owning its bus name is in the app's
+// process tree. Flatpaks talk through a proxy that isn't, for those the name
+// after the prefix, `firefox` out of `firefox.instance_2_41`, has to be the
+// app's.
+pub(crate) fn player_is_ours(bus_name: &str, owner: Option<u32>, pids: &HashSet<u32>, app_name: &str) -> bool {
+    let Some(player) = bus_name.strip_prefix(PLAYER_PREFIX) else {
+        return false;
+    };
+    owner.is_some_and(|owner| pids.contains(&owner))
+    || player.split('.').next().is_some_and(|player| player.eq_ignore_ascii_case(app_name))
+}
+
+// Failing that the player's Identity, "Mozilla Firefox" or "mpv", only has
+// to mention the app.
+pub(crate) fn identity_matches(identity: &str, app_name: &str) -> bool {
+    !app_name.is_empty() && identity.to_lowercase().contains(&app_name.to_lowercase())
+}
+
+fn list_names(connection: &Connection) -> Result<Vec<String>, zbus::Error> {
+    connection.call_method(
+        Some("org.freedesktop.DBus"),
+        "/org/freedesktop/DBus",
+        Some("org.freedesktop.DBus"),
+        "ListNames",
+        &()
+    )
+    .and_then(|reply| reply.body().deserialize())
+}
+
+fn owner_pid(connection: &Connection, bus_name: &str) -> Result<u32, zbus::Error> {
+    connection.call_method(
+        Some("org.freedesktop.DBus"),
+        "/org/freedesktop/DBus",
+        Some("org.freedesktop.DBus"),
+        "GetConnectionUnixProcessID",
+        &(bus_name,)
+    )
+    .and_then(|reply| reply.body().deserialize())
+}
+
+fn player_property(connection: &Connection, bus_name: &str, interface: &str, name: &str) -> Result<String, zbus::Error> {
+    let value: OwnedValue =
+    connection.call_method(
+        Some(bus_name),
+        "/org/mpris/MediaPlayer2",
+        Some("org.freedesktop.DBus.Properties"),
+        "Get",
+        &(interface, name)
+    )
+    .and_then(|reply| reply.body().deserialize())?;
+    Ok(String::try_from(value)?)
+}
+
+// --track-mpris asks the app's MPRIS players whether they're playing, which
+// they say with the sound muted, paused between tracks of a gapless album
+// or with no sound at all. Only `Playing` counts, a paused player that's
+// still loaded doesn't. The connection is made on the first look and kept.
+pub(crate) fn is_mpris_playing(pids: &HashSet<u32>, app_name: &str, state: &mut State) -> Result<bool, AttentionError> {
+    let connection = match state.mpris.take() {
+        Some(connection) => connection,
+        None => Connection::session().map_err(AttentionError::Dbus)?
+    };
+    let playing = players_playing(&connection, pids, app_name);
+    state.mpris = Some(connection);
+    playing
+}
+
+fn players_playing(connection: &Connection, pids: &HashSet<u32>, app_name: &str) -> Result<bool, AttentionError> {
+    let names = list_names(connection).map_err(AttentionError::Dbus)?;
+    for name in names.iter().filter(|name| name.starts_with(PLAYER_PREFIX)) {
+        let owner = owner_pid(connection, name).ok();
+        let ours = player_is_ours(name, owner, pids, app_name)
+        || player_property(connection, name, "org.mpris.MediaPlayer2", "Identity").is_ok_and(|identity| identity_matches(&identity, app_name));
+        if !ours {
+            continue;
+        }
+        // A player going away between the calls just doesn't count.
+        match player_property(connection, name, "org.mpris.MediaPlayer2.Player", "PlaybackStatus") {
+            Ok(status) if status == "Playing" => return Ok(true),
+            Ok(_) => (),
+            Err(err) => debug!("Couldn't ask {} what it's doing: {}..", name, err)
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn players_are_matched_by_owner_or_name() {
+        let pids = HashSet::from([9876, 9877]);
+        assert!(player_is_ours("org.mpris.MediaPlayer2.mpv", Some(9877), &pids, "vlc"));
+        assert!(player_is_ours("org.mpris.MediaPlayer2.firefox.instance_2_41", Some(1), &pids, "Firefox"));
+        assert!(player_is_ours("org.mpris.MediaPlayer2.mpv", None, &pids, "mpv"));
+        assert!(!player_is_ours("org.mpris.MediaPlayer2.spotify", Some(3003), &pids, "mpv"));
+        assert!(!player_is_ours("org.freedesktop.Notifications", Some(9876), &pids, "mpv"));
+        assert!(identity_matches("Mozilla Firefox", "firefox"));
+        assert!(!identity_matches("Spotify", "mpv"));
+        assert!(!identity_matches("Spotify", ""));
+    }
+}
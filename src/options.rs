@@ -0,0 +1,641 @@
+use core::time;
+use std::path::PathBuf;
+
+use chrono::{Local, NaiveTime, TimeDelta};
+use clap::error::ErrorKind;
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand};
+use regex::{Regex, RegexBuilder};
+
+use crate::audio::{AudioBackend, AudioRole, DEFAULT_MATCH_PROPS};
+use crate::condition::{parse_condition, Condition};
+use crate::error::AttentionError;
+use crate::inhibit::{InhibitMethod, InhibitScope, Urgency, DEFAULT_OFF_TEXT, DEFAULT_ON_TEXT, DEFAULT_REASON_TEXT};
+use crate::window::{window_id_number, MatchBy};
+
+pub enum Target {
+    Launch,
+    AttachPid(u32),
+    AttachTitle(String),
+    AttachRegex(Regex),
+    AttachWindow(String),
+    AttachApps(Vec<String>),
+    // --for and --until, no app at all.
+    Timed(time::Duration)
+}
+
+// A start and end time of day, the end is exclusive and the range wraps
+// past midnight when it's earlier than the start. The same time twice is
+// the whole day.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActiveHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime
+}
+
+impl ActiveHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start == self.end || (self.start <= time && time < self.end)
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+// The commit and date come from build.rs.
+const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), " (", env!("ATTENTION_GIT_COMMIT"), ", built ", env!("ATTENTION_BUILD_DATE"), ")");
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List the open windows with the id, PID and title to match on
+    List {
+        /// Also look up each window's WM_CLASS, or app_id on sway
+        #[arg(long)]
+        class: bool
+    },
+    /// Run every check once against the app's window and print what each one saw
+    Debug {
+        /// Matched against window titles, like the app name
+        app: String
+    },
+    /// Ask the running instance whether it's keeping the screen on
+    Status,
+    /// Let the screen blank until resumed, without stopping the running instance
+    Pause,
+    /// Go back to keeping the screen on when the app wants it
+    Resume,
+    /// Pause or resume, whichever the running instance isn't
+    Toggle,
+    /// Print the running instance's counters in Prometheus' text format
+    Metrics,
+    /// Ask the running instance to put blanking back and exit, like a detached one
+    Stop {
+        /// Send SIGTERM to the PID in this file instead of asking over the socket
+        #[arg(long, value_name = "PATH")]
+        pidfile: Option<PathBuf>
+    },
+    /// Write a systemd user unit that runs a config profile with the session
+    InstallService {
+        /// The [profiles.<name>] table the service runs
+        #[arg(long, value_name = "NAME")]
+        profile: String
+    }
+}
+
+#[derive(Parser)]
+#[command(name = "attention", about = "Disable screen blanking when fullscreen or playing audio", version = VERSION)]
+#[command(after_help = "Exit codes:
+  0  the tracked window closed
+  1  any other error
+  2  bad arguments or config
+  3  a command attention needs isn't installed
+  4  the app's window never appeared
+  5  an external command failed
+  6  --once left the screen free to blank")]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true, subcommand_precedence_over_arg = true)]
+#[command(group(ArgGroup::new("tracking").multiple(true).args(["track_audio", "track_fullscreen", "track_mpris"])))]
+pub struct Options {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Track audio to disable power management
+    #[arg(long)]
+    pub track_audio: bool,
+
+    /// Track fullscreen to disable power management
+    #[arg(long)]
+    pub track_fullscreen: bool,
+
+    /// Track whether the app's media player says it's playing over MPRIS, muted or not
+    #[arg(long, alias = "track-video")]
+    pub track_mpris: bool,
+
+    /// Keep the screen on while this holds, e.g. "fullscreen && audio" or "audio || idle<60s", instead of --track-audio/--track-fullscreen
+    #[arg(long, value_name = "EXPR", value_parser = parse_condition, conflicts_with = "tracking")]
+    pub condition: Option<Condition>,
+
+    /// Use a [profiles.<name>] table from the config file
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Track an already running window by its PID instead of launching
+    #[arg(long, value_name = "PID", conflicts_with = "attach_title")]
+    pub attach: Option<u32>,
+
+    /// Track several already running windows by title, the screen stays on while any of them wants it
+    #[arg(long, value_name = "NAMES", value_delimiter = ',', conflicts_with_all = ["attach", "attach_title", "match_regex", "persist", "focused_only", "app_name"])]
+    pub apps: Vec<String>,
+
+    /// Track this very window, an id like 0x03a00006 from wmctrl -l, instead of launching or looking for one
+    #[arg(long, value_name = "ID", value_parser = parse_window_id, conflicts_with_all = ["attach", "attach_title", "apps", "match_regex", "match_class"])]
+    pub window_id: Option<String>,
+
+    /// Track an already running window by its title instead of launching
+    #[arg(long, value_name = "SUBSTRING")]
+    pub attach_title: Option<String>,
+
+    /// Match windows on this exact WM_CLASS (app_id on sway) instead of the title
+    #[arg(long, value_name = "CLASS", conflicts_with = "attach_title")]
+    pub match_class: Option<String>,
+
+    /// What tells the app's window is still there: its PID, its title (or --match-class/--match-regex) or both
+    #[arg(long = "match", value_enum, value_name = "BY", default_value_t = MatchBy::Both)]
+    pub match_by: MatchBy,
+
+    /// Treat the app name as a regex for window titles and stream names, and attach instead of launching
+    #[arg(long, conflicts_with_all = ["attach", "attach_title", "match_class"])]
+    pub match_regex: bool,
+
+    /// Milliseconds between checks
+    #[arg(long, value_name = "MS", default_value = "1000", value_parser = parse_millis)]
+    pub interval: time::Duration,
+
+    /// Milliseconds between audio checks, --interval when not given
+    #[arg(long, value_name = "MS", value_parser = parse_millis)]
+    pub audio_interval: Option<time::Duration>,
+
+    /// Milliseconds between fullscreen checks, --interval when not given
+    #[arg(long, value_name = "MS", value_parser = parse_millis)]
+    pub fullscreen_interval: Option<time::Duration>,
+
+    /// Milliseconds between checks for the window to show up
+    #[arg(long, value_name = "MS", default_value = "200", value_parser = parse_millis)]
+    pub startup_interval: time::Duration,
+
+    /// Seconds a vanished window's app gets to show it again while still running, before it counts as closed
+    #[arg(long, value_name = "SECONDS", default_value = "5", value_parser = parse_seconds)]
+    pub window_gone_grace: time::Duration,
+
+    /// Wait for the X server to come back when it goes away, e.g. while the compositor restarts (the default)
+    #[arg(long, overrides_with = "no_reconnect")]
+    pub reconnect: bool,
+
+    /// Exit as soon as the X server goes away instead of waiting for it
+    #[arg(long, overrides_with = "reconnect")]
+    pub no_reconnect: bool,
+
+    /// Seconds to wait for the X server to come back before giving up
+    #[arg(long, value_name = "SECONDS", default_value = "60", value_parser = parse_seconds)]
+    pub reconnect_timeout: time::Duration,
+
+    /// Seconds to wait for the launched app's window before giving up
+    #[arg(long, value_name = "SECONDS", default_value = "30", value_parser = parse_seconds)]
+    pub window_timeout: time::Duration,
+
+    /// Shell command telling the launched app is ready by exiting 0, its window is only looked for after that
+    #[arg(long, value_name = "COMMAND", conflicts_with_all = ["attach", "attach_title", "window_id", "apps", "match_regex"])]
+    pub app_ready_cmd: Option<String>,
+
+    /// The X display to talk to, e.g. :0, instead of $DISPLAY
+    #[arg(long, value_name = "DISPLAY")]
+    pub display: Option<String>,
+
+    /// Seconds an external command like wmctrl or pactl gets before it's killed
+    #[arg(long, value_name = "SECONDS", default_value = "5", value_parser = parse_positive_seconds)]
+    pub command_timeout: time::Duration,
+
+    /// How many more times a failing window query is tried, with a growing wait in between, before giving up
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    pub max_retries: u32,
+
+    /// Seconds of continuous silence before audio counts as stopped
+    #[arg(long, value_name = "SECONDS", default_value = "0", value_parser = parse_seconds)]
+    pub grace: time::Duration,
+
+    /// Seconds after the window shows up during which blanking isn't given back, while the app gets going
+    #[arg(long, alias = "wait-for-audio-start", value_name = "SECONDS", default_value = "0", value_parser = parse_seconds)]
+    pub startup_grace: time::Duration,
+
+    /// Seconds after the window shows up during which its audio doesn't count, for apps that play a chime at launch
+    #[arg(long, value_name = "SECONDS", default_value = "0", value_parser = parse_seconds)]
+    pub warmup_ignore_audio: time::Duration,
+
+    /// Turn off blanking as soon as the window shows up, until the trackers have had their first look
+    #[arg(long)]
+    pub inhibit_on_start: bool,
+
+    /// Only count fullscreen on this output, a name from xrandr like DP-1 (X11)
+    #[arg(long, value_name = "NAME")]
+    pub output: Option<String>,
+
+    /// Polls in a row a fullscreen change has to be seen for before it counts
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub fullscreen_confirm: u32,
+
+    /// Only keep the screen on while the app's window has focus
+    #[arg(long)]
+    pub focused_only: bool,
+
+    /// Give blanking back after this many seconds without input, even while the app wants it off (X11, needs xprintidle)
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    pub idle_release: Option<time::Duration>,
+
+    /// Look once, leave blanking the way that look wants it and exit instead of tracking
+    #[arg(long, conflicts_with_all = ["persist", "for_duration", "until"])]
+    pub once: bool,
+
+    /// Keep the screen on for this long without tracking any app, e.g. 90s, 30m or 2h
+    #[arg(long = "for", value_name = "DURATION", value_parser = parse_duration, conflicts_with_all = ["until", "attach", "attach_title", "apps"])]
+    pub for_duration: Option<time::Duration>,
+
+    /// Keep the screen on until this local time without tracking any app, e.g. 23:30
+    #[arg(long, value_name = "HH:MM", value_parser = parse_time, conflicts_with_all = ["attach", "attach_title", "apps"])]
+    pub until: Option<NaiveTime>,
+
+    /// Only keep the screen on between these local times, e.g. 09:00-17:00 or 22:00-06:00
+    #[arg(long, value_name = "HH:MM-HH:MM", value_parser = parse_active_hours)]
+    pub active_hours: Option<ActiveHours>,
+
+    /// Only keep the screen on while plugged in, let it blank on battery
+    #[arg(long)]
+    pub ac_only: bool,
+
+    /// Shell command run when blanking gets turned off, with ATTENTION_APP and ATTENTION_REASON set
+    #[arg(long, value_name = "COMMAND")]
+    pub on_inhibit: Option<String>,
+
+    /// Shell command run when blanking is back on, with ATTENTION_APP and ATTENTION_REASON set
+    #[arg(long, value_name = "COMMAND")]
+    pub on_release: Option<String>,
+
+    /// Run the app and its arguments as one command line through sh -c
+    #[arg(long)]
+    pub shell: bool,
+
+    /// Set a variable in the launched app's environment on top of ours, can be given more than once
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env)]
+    pub env: Vec<(String, String)>,
+
+    /// Launch the app in this directory instead of ours
+    #[arg(long, value_name = "DIR")]
+    pub cwd: Option<PathBuf>,
+
+    /// Leave the terminal and keep tracking in the background, logging to --log-file or $XDG_RUNTIME_DIR/attention.log, until `attention stop`
+    #[arg(long, overrides_with = "until_closed")]
+    pub detach: bool,
+
+    /// Stay in the foreground until the app is done, the default, undoes an earlier --detach
+    #[arg(long, overrides_with = "detach")]
+    pub until_closed: bool,
+
+    /// Write our own log to this file instead of stderr
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Size the log file grows to before it's moved to <PATH>.1 and started over, like 512K or 10M, unused while logging to stderr
+    #[arg(long, value_name = "SIZE", default_value = "10M", value_parser = parse_size)]
+    pub log_max_size: u64,
+
+    /// Append the launched app's stdout and stderr to this file instead of discarding them
+    #[arg(long, value_name = "PATH")]
+    pub app_log: Option<PathBuf>,
+
+    /// Remember whether blanking was off across restarts
+    #[arg(long)]
+    pub restore_state: bool,
+
+    /// Write our PID to this file, for init scripts and start-stop-daemon, and remove it on exit
+    #[arg(long, value_name = "PATH")]
+    pub pidfile: Option<PathBuf>,
+
+    /// Ask an already running instance to exit instead of refusing to start
+    #[arg(long)]
+    pub replace: bool,
+
+    /// Where to look for the app's audio streams, auto takes pipewire when PipeWire runs the session and pw-dump is installed, pactl subscribe otherwise
+    #[arg(long, value_enum, default_value_t = AudioBackend::Auto)]
+    pub audio_backend: AudioBackend,
+
+    /// Stream properties --match-regex is matched against, ignoring case
+    #[arg(long, value_name = "PROP", value_delimiter = ',', default_values_t = DEFAULT_MATCH_PROPS.map(str::to_owned))]
+    pub audio_match_prop: Vec<String>,
+
+    /// Only count streams whose media.role says this, so a notification sound doesn't keep the screen on
+    #[arg(long, value_enum, value_name = "ROLE", default_value_t = AudioRole::Any)]
+    pub audio_role: AudioRole,
+
+    /// Wait for the app to open a new window when its window closes, only exit once its process is gone
+    #[arg(long)]
+    pub persist: bool,
+
+    /// Start the launched app again whenever it dies instead of exiting with it, a clean exit still ends tracking
+    #[arg(long, conflicts_with_all = ["attach", "attach_title", "window_id", "apps", "match_regex", "once"])]
+    pub restart_app: bool,
+
+    /// Give up once the app has been restarted this many times
+    #[arg(long, value_name = "N", requires = "restart_app")]
+    pub max_restarts: Option<u32>,
+
+    /// Stop the launched app when attention exits, SIGTERM first, SIGKILL if it lingers
+    #[arg(long)]
+    pub kill_on_exit: bool,
+
+    /// Every this many seconds, log what each check says about the window, with --verbose
+    #[arg(long, value_name = "SECONDS", value_parser = parse_positive_seconds)]
+    pub debug_dump: Option<time::Duration>,
+
+    /// Also log every command that's run and how it exited
+    #[arg(short, long, conflicts_with = "quiet")]
+    pub verbose: bool,
+
+    /// Only log errors
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Track as usual but only log what would be done to screen blanking
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Print state changes as one JSON object per line
+    #[arg(long)]
+    pub json: bool,
+
+    /// How to keep the screen from blanking, xset means D-Bus on Hyprland and the GNOME session on GNOME Wayland
+    #[arg(long, value_enum, default_value_t = InhibitMethod::Xset)]
+    pub inhibit_method: InhibitMethod,
+
+    /// What to hold off besides the display: idle for logind's idle action, lock for the screensaver and its lock, all for both and suspend
+    #[arg(long, value_enum, value_name = "WHAT", default_value_t = InhibitScope::Display)]
+    pub inhibit: InhibitScope,
+
+    /// What the dbus, gnome and logind inhibitors say they're for, as systemd-inhibit --list shows it, {app} is the app's name and {reason} why
+    #[arg(long, value_name = "TEXT", default_value = DEFAULT_REASON_TEXT)]
+    pub reason: String,
+
+    /// Don't send desktop notifications when blanking changes
+    #[arg(long)]
+    pub no_notify: bool,
+
+    /// Notification sent when blanking is turned off, {app} is the app's name and {reason} why
+    #[arg(long, value_name = "TEXT", default_value = DEFAULT_ON_TEXT)]
+    pub notify_on_text: String,
+
+    /// Notification sent when blanking is back on, {app} is the app's name and {reason} what it was held for
+    #[arg(long, value_name = "TEXT", default_value = DEFAULT_OFF_TEXT)]
+    pub notify_off_text: String,
+
+    /// Icon name or path passed to notify-send
+    #[arg(long, value_name = "ICON")]
+    pub notify_icon: Option<String>,
+
+    /// Urgency passed to notify-send
+    #[arg(long, value_enum)]
+    pub notify_urgency: Option<Urgency>,
+
+    /// Milliseconds before a notification goes away on its own, passed to notify-send
+    #[arg(long, value_name = "MS", value_parser = parse_millis)]
+    pub notify_timeout: Option<time::Duration>,
+
+    /// Replace the last notification instead of stacking a new one on top of it
+    #[arg(long)]
+    pub notify_replace: bool,
+
+    /// Seconds before another notification of the same kind is sent, changes in between happen quietly
+    #[arg(long, value_name = "SECONDS", default_value = "0", value_parser = parse_seconds)]
+    pub notify_cooldown: time::Duration,
+
+    /// The app to launch, or to name the attached window after
+    #[arg(required_unless_present_any = ["attach", "attach_title", "window_id", "apps", "profile", "for_duration", "until"])]
+    pub app_name: Option<String>,
+
+    /// Arguments passed on to the app
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    pub app_args: Vec<String>
+}
+
+impl Options {
+    // The trackers and the app can come from a profile, so whether they're
+    // there is only known once the config is applied.
+    pub fn validate(&self) -> Result<(), clap::Error> {
+        if self.timed() {
+            return Ok(());
+        }
+        if self.condition().is_none() && !self.track_mpris {
+            return Err(Self::command().error(ErrorKind::MissingRequiredArgument, "one of --track-audio, --track-fullscreen, --track-mpris or --condition is required"));
+        }
+        if self.app_name.is_none() && !self.attaches() {
+            return Err(Self::command().error(ErrorKind::MissingRequiredArgument, "an app to launch is required unless attaching"));
+        }
+        // Attached by PID there's no name, any window would do.
+        if self.match_by == MatchBy::Name && self.attach.is_some() && self.match_class.is_none() {
+            return Err(Self::command().error(ErrorKind::ArgumentConflict, "--match name needs a title or --match-class to go by, --attach only gives a PID"));
+        }
+        // Only xset outlives us, the other methods end with the process.
+        if self.once && (self.inhibit_method != InhibitMethod::Xset || !self.attaches()) {
+            return Err(Self::command().error(ErrorKind::ArgumentConflict, "--once needs --inhibit-method xset and an app to attach to"));
+        }
+        Ok(())
+    }
+
+    pub fn attaches(&self) -> bool {
+        self.attach.is_some() || self.attach_title.is_some() || self.window_id.is_some() || self.match_regex || !self.apps.is_empty()
+    }
+
+    pub fn timed(&self) -> bool {
+        self.for_duration.is_some() || self.until.is_some()
+    }
+
+    // The flags are shortcuts for `audio`, `fullscreen` or both or'd.
+    pub fn condition(&self) -> Option<Condition> {
+        self.condition.clone().or_else(|| Condition::from_flags(self.track_audio, self.track_fullscreen))
+    }
+
+    // The regex is compiled here, once, so a bad one fails before anything
+    // else happens.
+    pub fn target(&self) -> Result<Target, AttentionError> {
+        if let Some(duration) = self.for_duration {
+            Ok(Target::Timed(duration))
+        } else if let Some(until) = self.until {
+            // A time that's already gone today means tomorrow.
+            let now = Local::now().time();
+            let left = (until - now).to_std().unwrap_or_else(|_| (until - now + TimeDelta::days(1)).to_std().unwrap_or_default());
+            Ok(Target::Timed(left))
+        } else if let Some(pid) = self.attach {
+            Ok(Target::AttachPid(pid))
+        } else if let Some(id) = &self.window_id {
+            Ok(Target::AttachWindow(id.clone()))
+        } else if let Some(title) = &self.attach_title {
+            Ok(Target::AttachTitle(title.clone()))
+        } else if !self.apps.is_empty() {
+            Ok(Target::AttachApps(self.apps.clone()))
+        } else if self.match_regex {
+            let pattern = self.app_name.clone().unwrap_or_default();
+            // Like plain titles, a regex doesn't care about case.
+            RegexBuilder::new(&pattern)
+            .case_insensitive(true)
+            .build()
+            .map(Target::AttachRegex)
+            .map_err(|err| AttentionError::InvalidRegex { pattern, reason: err.to_string() })
+        } else {
+            Ok(Target::Launch)
+        }
+    }
+}
+
+pub(crate) fn parse_window_id(value: &str) -> Result<String, String> {
+    match window_id_number(value) {
+        Some(_) => Ok(value.to_owned()),
+        None => Err("expected a window id like 0x03a00006".to_owned())
+    }
+}
+
+pub(crate) fn parse_millis(value: &str) -> Result<time::Duration, String> {
+    match value.parse::<u64>() {
+        Ok(millis) if millis > 0 => Ok(time::Duration::from_millis(millis)),
+        _ => Err("expected a positive number of milliseconds".to_owned())
+    }
+}
+
+pub(crate) fn parse_seconds(value: &str) -> Result<time::Duration, String> {
+    value.parse::<u64>()
+    .map(time::Duration::from_secs)
+    .map_err(|_| "expected a number of seconds".to_owned())
+}
+
+// A bare number is seconds, otherwise one of s, m or h has to follow.
+pub(crate) fn parse_duration(value: &str) -> Result<time::Duration, String> {
+    let invalid = || "expected a duration like 90s, 30m or 2h".to_owned();
+    let value = value.trim();
+    let (amount, scale) = match value.char_indices().last() {
+        Some((at, 's')) => (&value[..at], 1),
+        Some((at, 'm')) => (&value[..at], 60),
+        Some((at, 'h')) => (&value[..at], 60 * 60),
+        _ => (value, 1)
+    };
+    match amount.trim().parse::<u64>().ok().filter(|&amount| amount > 0).and_then(|amount| amount.checked_mul(scale)) {
+        Some(seconds) => Ok(time::Duration::from_secs(seconds)),
+        None => Err(invalid())
+    }
+}
+
+// Bytes, or kibi-, mebi- or gibibytes with K, M or G after the number.
+pub(crate) fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (amount, scale) = match value.char_indices().last() {
+        Some((at, 'K' | 'k')) => (&value[..at], 1 << 10),
+        Some((at, 'M' | 'm')) => (&value[..at], 1 << 20),
+        Some((at, 'G' | 'g')) => (&value[..at], 1 << 30),
+        _ => (value, 1)
+    };
+    match amount.parse::<u64>().ok().filter(|&amount| amount > 0).and_then(|amount| amount.checked_mul(scale)) {
+        Some(size) => Ok(size),
+        None => Err("expected a size like 512K or 10M".to_owned())
+    }
+}
+
+// The value can hold more `=`, only the first one ends the name.
+pub(crate) fn parse_env(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+        _ => Err("expected a variable like LIBVA_DRIVER_NAME=iHD".to_owned())
+    }
+}
+
+pub(crate) fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").map_err(|_| "expected a time like 23:30".to_owned())
+}
+
+pub(crate) fn parse_active_hours(value: &str) -> Result<ActiveHours, String> {
+    let invalid = || "expected two times like 09:00-17:00".to_owned();
+    let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+    let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").map_err(|_| invalid())?;
+    let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").map_err(|_| invalid())?;
+    Ok(ActiveHours { start, end })
+}
+
+pub(crate) fn parse_positive_seconds(value: &str) -> Result<time::Duration, String> {
+    match value.parse::<u64>() {
+        Ok(seconds) if seconds > 0 => Ok(time::Duration::from_secs(seconds)),
+        _ => Err("expected a positive number of seconds".to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_is_only_a_subcommand_in_front_of_everything() {
+        let options = Options::try_parse_from(["attention", "list", "--class"]).unwrap();
+        assert!(matches!(options.command, Some(Command::List { class: true })));
+
+        let options = Options::try_parse_from(["attention", "--track-audio", "mpv", "list"]).unwrap();
+        assert!(options.command.is_none());
+        assert_eq!(options.app_args, vec!["list"]);
+    }
+
+    #[test]
+    fn once_only_attaches_with_xset() {
+        let validate = |args: &[&str]| Options::try_parse_from(args).unwrap().validate().is_ok();
+        assert!(validate(&["attention", "--once", "--track-audio", "--attach", "9876"]));
+        assert!(!validate(&["attention", "--once", "--track-audio", "mpv"]));
+        assert!(!validate(&["attention", "--once", "--track-audio", "--inhibit-method", "dbus", "--attach", "9876"]));
+    }
+
+    #[test]
+    fn a_timed_run_needs_no_app_or_tracker() {
+        let options = Options::try_parse_from(["attention", "--for", "30m"]).unwrap();
+        assert!(options.validate().is_ok());
+        assert!(matches!(options.target(), Ok(Target::Timed(duration)) if duration == time::Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("2h"), Ok(time::Duration::from_secs(2 * 60 * 60)));
+        assert_eq!(parse_duration("90"), Ok(time::Duration::from_secs(90)));
+        assert_eq!(parse_duration(" 30m"), Ok(time::Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("\u{3000}30m"), Ok(time::Duration::from_secs(30 * 60)));
+        assert!(parse_duration("99999999999999999h").is_err());
+        assert!(parse_duration("0s").is_err() && parse_duration("soon").is_err());
+        assert!(Options::try_parse_from(["attention", "--for", "30m", "--until", "23:30"]).is_err());
+    }
+
+    #[test]
+    fn a_window_id_is_checked_up_front() {
+        let options = Options::try_parse_from(["attention", "--track-fullscreen", "--window-id", "0x03a00006"]).unwrap();
+        assert!(options.validate().is_ok());
+        assert!(matches!(options.target(), Ok(Target::AttachWindow(id)) if id == "0x03a00006"));
+        assert!(Options::try_parse_from(["attention", "--track-fullscreen", "--window-id", "mpv"]).is_err());
+        assert!(Options::try_parse_from(["attention", "--track-fullscreen", "--window-id", "0x3a", "--attach", "42"]).is_err());
+    }
+
+    #[test]
+    fn match_regex_attaches_and_rejects_bad_patterns() {
+        let options = Options::try_parse_from(["attention", "--track-audio", "--match-regex", "YouTube|Spotify"]).unwrap();
+        assert!(matches!(options.target(), Ok(Target::AttachRegex(regex)) if regex.is_match("Spotify") && regex.is_match("spotify")));
+
+        let options = Options::try_parse_from(["attention", "--track-audio", "--match-regex", "(YouTube"]).unwrap();
+        assert!(matches!(options.target(), Err(AttentionError::InvalidRegex { .. })));
+    }
+
+    #[test]
+    fn the_log_size_goes_with_the_detached_log_too() {
+        let options = Options::try_parse_from(["attention", "--detach", "--log-max-size", "1M", "--for", "1m"]).unwrap();
+        assert!(options.log_file.is_none());
+        assert_eq!(options.log_max_size, 1 << 20);
+        assert_eq!(parse_size("512K"), Ok(512 << 10));
+        assert!(parse_size("99999999999999999G").is_err() && parse_size("0").is_err());
+    }
+
+    #[test]
+    fn the_last_of_detach_and_until_closed_wins() {
+        let options = Options::try_parse_from(["attention", "--track-audio", "--detach", "--until-closed", "mpv"]).unwrap();
+        assert!(!options.detach && options.until_closed);
+        let options = Options::try_parse_from(["attention", "--track-audio", "--until-closed", "--detach", "mpv"]).unwrap();
+        assert!(options.detach && !options.until_closed);
+    }
+
+    #[test]
+    fn env_is_given_as_key_and_value() {
+        let options = Options::try_parse_from(["attention", "--track-audio", "--env", "LANG=C", "--env", "MPV_OPTS=--vo=gpu", "mpv"]).unwrap();
+        assert_eq!(options.env, [("LANG".to_owned(), "C".to_owned()), ("MPV_OPTS".to_owned(), "--vo=gpu".to_owned())]);
+        assert_eq!(parse_env("EMPTY="), Ok(("EMPTY".to_owned(), String::new())));
+        assert!(parse_env("LANG").is_err());
+        assert!(parse_env("=C").is_err());
+    }
+
+    #[test]
+    fn active_hours_are_two_times_of_day() {
+        let hours = parse_active_hours("22:00-06:30").unwrap();
+        assert_eq!(hours.start, NaiveTime::from_hms_opt(22, 0, 0).unwrap());
+        assert_eq!(hours.end, NaiveTime::from_hms_opt(6, 30, 0).unwrap());
+        assert!(parse_active_hours("22:00").is_err());
+        assert!(parse_active_hours("25:00-06:00").is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use crate::error::AttentionError;
+use crate::runner::{run_command, CommandRunner};
+
+// A rectangle on the X screen, the way xrandr and xwininfo place things.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) struct Geometry {
+    pub x: i64,
+    pub y: i64,
+    pub width: i64,
+    pub height: i64
+}
+
+impl Geometry {
+    // A window spanning two outputs is on the one holding its middle.
+    fn holds_center_of(&self, other: &Geometry) -> bool {
+        let (x, y) = (other.x + other.width / 2, other.y + other.height / 2);
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+// `xwininfo -id` prints one `Name:  value` per line, the absolute position
+// and the size are all we need.
+pub(crate) fn parse_xwininfo(stdout: &str) -> Option<Geometry> {
+    let value = |name: &str| {
+        stdout.lines()
+        .find_map(|line| line.trim().strip_prefix(name))
+        .and_then(|value| value.trim().parse().ok())
+    };
+    Some(Geometry {
+        x: value("Absolute upper-left X:")?,
+        y: value("Absolute upper-left Y:")?,
+        width: value("Width:")?,
+        height: value("Height:")?
+    })
+}
+
+// `DP-1 connected primary 2560x1440+1920+0 (normal left ...) 597mm x 336mm`,
+// a connected output that's turned off has no geometry and isn't found.
+pub(crate) fn parse_xrandr_output(stdout: &str, output: &str) -> Option<Geometry> {
+    let line = stdout.lines().find(|line| line.split_whitespace().next() == Some(output) && line.contains(" connected"))?;
+    line.split_whitespace().find_map(|word| {
+        let (size, position) = word.split_once('+')?;
+        let (width, height) = size.split_once('x')?;
+        let (x, y) = position.split_once('+')?;
+        Some(Geometry { x: x.parse().ok()?, y: y.parse().ok()?, width: width.parse().ok()?, height: height.parse().ok()? })
+    })
+}
+
+// Whether the window sits on `output`, an output that isn't connected holds
+// nothing.
+pub(crate) fn window_on_output(runner: &dyn CommandRunner, window_id: &str, output: &str) -> Result<bool, AttentionError> {
+    let window = run_command(runner, "xwininfo", &["-id", window_id])?;
+    let window = parse_xwininfo(&String::from_utf8_lossy(&window.stdout))
+    .ok_or_else(|| AttentionError::UnexpectedOutput { cmd: "xwininfo".to_owned(), reason: "no window geometry".to_owned() })?;
+    let outputs = run_command(runner, "xrandr", &["--query"])?;
+    Ok(parse_xrandr_output(&String::from_utf8_lossy(&outputs.stdout), output)
+    .is_some_and(|output| output.holds_center_of(&window)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::MockRunner;
+
+    const XWININFO: &str = "\
+xwininfo: Window id: 0x03a00006 \"video.mkv - mpv\"
+
+  Absolute upper-left X:  1920
+  Absolute upper-left Y:  0
+  Relative upper-left X:  0
+  Relative upper-left Y:  0
+  Width: 2560
+  Height: 1440
+  Depth: 24
+";
+
+    const XRANDR: &str = "\
+Screen 0: minimum 320 x 200, current 4480 x 1440, maximum 16384 x 16384
+HDMI-1 connected primary 1920x1080+0+0 (normal left inverted right x axis y axis) 527mm x 296mm
+   1920x1080     60.00*+
+DP-1 connected 2560x1440+1920+0 (normal left inverted right x axis y axis) 597mm x 336mm
+   2560x1440     59.95*+
+DP-2 disconnected (normal left inverted right x axis y axis)
+";
+
+    #[test]
+    fn geometries_are_parsed_from_xwininfo_and_xrandr() {
+        assert_eq!(parse_xwininfo(XWININFO), Some(Geometry { x: 1920, y: 0, width: 2560, height: 1440 }));
+        assert_eq!(parse_xrandr_output(XRANDR, "HDMI-1"), Some(Geometry { x: 0, y: 0, width: 1920, height: 1080 }));
+        assert_eq!(parse_xrandr_output(XRANDR, "DP-2"), None);
+        assert_eq!(parse_xrandr_output(XRANDR, "DP"), None);
+    }
+
+    #[test]
+    fn a_window_is_on_the_output_holding_its_middle() {
+        let runner = MockRunner::new().with("xwininfo", XWININFO).with("xrandr", XRANDR);
+        assert!(window_on_output(&runner, "0x03a00006", "DP-1").unwrap());
+        assert!(!window_on_output(&runner, "0x03a00006", "HDMI-1").unwrap());
+        assert!(!window_on_output(&runner, "0x03a00006", "DP-2").unwrap());
+    }
+}
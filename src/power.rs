@@ -0,0 +1,41 @@
+use std::fs;
+use std::path::Path;
+
+// Only chargers report `online`, batteries don't. No charger at all is a
+// desktop, which is always on AC.
+pub(crate) fn any_online<'a>(onlines: impl IntoIterator<Item = &'a str>) -> bool {
+    let mut chargers = onlines.into_iter().peekable();
+    chargers.peek().is_none() || chargers.any(|online| online.trim() == "1")
+}
+
+// Read fresh every loop so unplugging is noticed on the next tick. A
+// wireless mouse reports its own `scope = Device` supply, that's no AC.
+pub(crate) fn on_ac_power() -> bool {
+    let Ok(entries) = fs::read_dir("/sys/class/power_supply") else {
+        return true;
+    };
+
+    let onlines: Vec<String> = entries
+    .filter_map(Result::ok)
+    .map(|entry| entry.path())
+    .filter(|supply| !is_device_scoped(supply))
+    .filter_map(|supply| fs::read_to_string(supply.join("online")).ok())
+    .collect();
+    any_online(onlines.iter().map(String::as_str))
+}
+
+fn is_device_scoped(supply: &Path) -> bool {
+    fs::read_to_string(supply.join("scope")).is_ok_and(|scope| scope.trim() == "Device")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn any_online_charger_means_ac() {
+        assert!(any_online(["0\n", "1\n"]));
+        assert!(!any_online(["0\n"]));
+        assert!(any_online([]));
+    }
+}
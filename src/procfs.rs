@@ -0,0 +1,89 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+// `/proc/<pid>/stat` is `pid (comm) state ppid ...`, comm can hold spaces and
+// parentheses itself so the fields are counted from the last `)`.
+pub(crate) fn parse_stat_ppid(stat: &str) -> Option<u32> {
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(1)?.parse().ok()
+}
+
+// `root` plus everything below it, given each process' parent.
+pub(crate) fn descendants(root: u32, parents: &HashMap<u32, u32>) -> HashSet<u32> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (&pid, &ppid) in parents {
+        children.entry(ppid).or_default().push(pid);
+    }
+
+    let mut tree = HashSet::from([root]);
+    let mut pending = vec![root];
+    while let Some(pid) = pending.pop() {
+        for &child in children.get(&pid).into_iter().flatten() {
+            if tree.insert(child) {
+                pending.push(child);
+            }
+        }
+    }
+    tree
+}
+
+// `state` is the field right after comm, a zombie has already exited.
+pub(crate) fn parse_stat_state(stat: &str) -> Option<char> {
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().next()?.chars().next()
+}
+
+pub(crate) fn is_alive(pid: u32) -> bool {
+    fs::read_to_string(format!("/proc/{}/stat", pid))
+    .ok()
+    .and_then(|stat| parse_stat_state(&stat))
+    .is_some_and(|state| state != 'Z' && state != 'X')
+}
+
+// The app counts as running while anything in its tree still is.
+pub(crate) fn tree_alive(root: u32) -> bool {
+    process_tree(root).into_iter().any(is_alive)
+}
+
+// Read fresh every time, children come and go while the app runs.
+pub(crate) fn process_tree(root: u32) -> HashSet<u32> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return HashSet::from([root]);
+    };
+
+    let parents: HashMap<u32, u32> = entries
+    .filter_map(Result::ok)
+    .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+    .filter_map(|pid| {
+        let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        Some((pid, parse_stat_ppid(&stat)?))
+    })
+    .collect();
+    descendants(root, &parents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parent_pid_is_read_past_the_comm() {
+        assert_eq!(parse_stat_ppid("2451 (firefox) S 1 2451 2451 0 -1"), Some(1));
+        assert_eq!(parse_stat_ppid("2502 (Web Content (1)) S 2451 2451 2451 0 -1"), Some(2451));
+        assert_eq!(parse_stat_ppid("garbage"), None);
+    }
+
+    #[test]
+    fn zombies_dont_count_as_alive() {
+        assert_eq!(parse_stat_state("2502 (Web Content (1)) Z 2451 2451 2451 0 -1"), Some('Z'));
+        assert!(is_alive(std::process::id()));
+        assert!(!is_alive(u32::MAX));
+    }
+
+    #[test]
+    fn descendants_are_collected_transitively() {
+        let parents = HashMap::from([(2451, 1), (2502, 2451), (2600, 2502), (3003, 1)]);
+        assert_eq!(descendants(2451, &parents), HashSet::from([2451, 2502, 2600]));
+        assert_eq!(descendants(4242, &parents), HashSet::from([4242]));
+    }
+}
@@ -0,0 +1,237 @@
+use core::time;
+use std::env;
+use std::io::{self, Read};
+#[cfg(unix)]
+use std::os::unix::process::ExitStatusExt;
+#[cfg(windows)]
+use std::os::windows::process::ExitStatusExt;
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use log::{debug, info, warn};
+
+use crate::error::AttentionError;
+
+pub trait CommandRunner {
+    fn run(&self, cmd: &str, args: &[&str]) -> io::Result<Output>;
+}
+
+// Every command gets `timeout` to finish, a hung X server or D-Bus shouldn't
+// freeze the loop with blanking still off. `display` replaces the DISPLAY we
+// were started with, for running from a systemd unit or cron.
+#[derive(Clone)]
+pub struct SystemRunner {
+    pub timeout: time::Duration,
+    pub display: Option<String>
+}
+
+impl Default for SystemRunner {
+    fn default() -> Self {
+        Self { timeout: time::Duration::from_secs(5), display: None }
+    }
+}
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, cmd: &str, args: &[&str]) -> io::Result<Output> {
+        let mut command = Command::new(cmd);
+        command.args(args);
+        if let Some(display) = &self.display {
+            command.env("DISPLAY", display);
+        }
+        run_with_timeout(&mut command, self.timeout)
+    }
+}
+
+fn drain(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+// The pipes are drained on their own threads while we wait, pw-dump can
+// print more than fits in a pipe buffer before it exits.
+pub(crate) fn run_with_timeout(command: &mut Command, timeout: time::Duration) -> io::Result<Output> {
+    let mut child = command
+    .stdin(Stdio::null())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()?;
+
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(io::ErrorKind::TimedOut, format!("no answer within {}s", timeout.as_secs_f32())));
+        }
+        thread::sleep(time::Duration::from_millis(10));
+    };
+    Ok(Output { status, stdout: stdout.join().unwrap_or_default(), stderr: stderr.join().unwrap_or_default() })
+}
+
+// Stands in for the runner with --dry-run, says what would have run and
+// pretends it worked.
+pub(crate) struct DryRunner;
+
+impl CommandRunner for DryRunner {
+    fn run(&self, cmd: &str, args: &[&str]) -> io::Result<Output> {
+        info!("[dry-run] would run: {} {}", cmd, args.join(" "));
+        Ok(Output { status: ExitStatus::from_raw(0), stdout: Vec::new(), stderr: Vec::new() })
+    }
+}
+
+pub(crate) fn in_path(cmd: &str) -> bool {
+    env::var_os("PATH")
+    .map(|paths| env::split_paths(&paths).any(|dir| dir.join(cmd).is_file()))
+    .unwrap_or(false)
+}
+
+pub(crate) fn run_command(runner: &dyn CommandRunner, cmd: &str, args: &[&str]) -> Result<Output, AttentionError> {
+    let output =
+    runner.run(cmd, args)
+    .map_err(|source| match source.kind() {
+        io::ErrorKind::TimedOut => AttentionError::CommandTimeout { cmd: cmd.to_owned() },
+        _ => AttentionError::CommandSpawn { cmd: cmd.to_owned(), source }
+    })?;
+    debug!("{} {} exited with {}", cmd, args.join(" "), output.status);
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        return Err(AttentionError::CommandFailed { cmd: cmd.to_owned(), stderr });
+    }
+
+    Ok(output)
+}
+
+// A command that failed or hung may just have been caught at a bad moment,
+// like wmctrl while the compositor restarts. It gets `retries` more tries,
+// waiting 200ms and doubling each time, anything else fails right away.
+pub(crate) fn with_retries<T>(retries: u32, mut attempt: impl FnMut() -> Result<T, AttentionError>) -> Result<T, AttentionError> {
+    let mut backoff = time::Duration::from_millis(200);
+    for _ in 0..retries {
+        match attempt() {
+            Err(err @ (AttentionError::CommandFailed { .. } | AttentionError::CommandTimeout { .. })) => {
+                warn!("{}, retrying in {}ms..", err, backoff.as_millis());
+                thread::sleep(backoff);
+                backoff *= 2;
+            }
+            result => return result
+        }
+    }
+    attempt()
+}
+
+#[cfg(test)]
+pub(crate) use mock::MockRunner;
+
+#[cfg(test)]
+mod mock {
+    use core::time;
+    use std::collections::HashMap;
+    use std::io;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::Mutex;
+    use std::thread;
+
+    use super::CommandRunner;
+
+    // Answers every command with a canned stdout and remembers what was run.
+    // Commands made slow take that long to answer.
+    pub(crate) struct MockRunner {
+        outputs: HashMap<String, Output>,
+        delays: HashMap<String, time::Duration>,
+        calls: Mutex<Vec<String>>
+    }
+
+    impl MockRunner {
+        pub(crate) fn new() -> Self {
+            Self { outputs: HashMap::new(), delays: HashMap::new(), calls: Mutex::new(Vec::new()) }
+        }
+
+        pub(crate) fn slow(mut self, cmd: &str, delay: time::Duration) -> Self {
+            self.delays.insert(cmd.to_owned(), delay);
+            self
+        }
+
+        pub(crate) fn with(mut self, cmd: &str, stdout: &str) -> Self {
+            self.outputs.insert(cmd.to_owned(), output(0, stdout, ""));
+            self
+        }
+
+        pub(crate) fn failing(mut self, cmd: &str, stderr: &str) -> Self {
+            self.outputs.insert(cmd.to_owned(), output(1, "", stderr));
+            self
+        }
+
+        pub(crate) fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl CommandRunner for MockRunner {
+        fn run(&self, cmd: &str, args: &[&str]) -> io::Result<Output> {
+            self.calls.lock().unwrap().push(format!("{} {}", cmd, args.join(" ")).trim_end().to_owned());
+            if let Some(&delay) = self.delays.get(cmd) {
+                thread::sleep(delay);
+            }
+            Ok(self.outputs.get(cmd).cloned().unwrap_or_else(|| output(0, "", "")))
+        }
+    }
+
+    fn output(code: i32, stdout: &str, stderr: &str) -> Output {
+        Output {
+            status: ExitStatus::from_raw(code << 8),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: stderr.as_bytes().to_vec()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_failed_commands_are_retried() {
+        let mut attempts = 0;
+        let result = with_retries(1, || {
+            attempts += 1;
+            match attempts {
+                1 => Err(AttentionError::CommandFailed { cmd: "wmctrl".to_owned(), stderr: "Cannot open display.".to_owned() }),
+                _ => Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 2);
+
+        let mut attempts = 0;
+        let result: Result<(), _> = with_retries(3, || {
+            attempts += 1;
+            Err(AttentionError::NoSession)
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn a_hung_command_is_killed_after_the_timeout() {
+        let output = run_with_timeout(Command::new("echo").arg("hello"), time::Duration::from_secs(5)).unwrap();
+        assert_eq!(output.stdout, b"hello\n");
+
+        let started = Instant::now();
+        let err = run_with_timeout(Command::new("sleep").arg("5"), time::Duration::from_millis(100)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < time::Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,75 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::inhibit::DpmsSettings;
+use crate::state::{ScreenBlankingState, State};
+
+// With --restore-state every blanking change is written here, so an instance
+// started after a crash or an update can pick up where the last one left off
+// instead of letting the screen blank in between.
+pub(crate) fn state_file() -> Option<PathBuf> {
+    env::var_os("XDG_RUNTIME_DIR")
+    .filter(|dir| !dir.is_empty())
+    .map(|dir| PathBuf::from(dir).join("attention.state"))
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedState {
+    pub pid: u32,
+    pub blanking: ScreenBlankingState,
+    pub app: String,
+    // The timeouts from before the last instance turned DPMS off, reading
+    // them now would only find it off.
+    pub dpms: Option<DpmsSettings>
+}
+
+// Whatever the last instance left behind, the instance lock makes sure it's
+// no longer around to disagree.
+pub(crate) fn load(path: &Path) -> Option<SavedState> {
+    fs::read_to_string(path).ok().and_then(|contents| serde_json::from_str(&contents).ok())
+}
+
+// Losing the file only costs the restore, so it's a warning.
+pub(crate) fn save(state: &State) {
+    let Some(path) = &state.state_file else {
+        return;
+    };
+    let saved = SavedState { pid: process::id(), blanking: state.last_screen_blanking_state, app: state.app_name.clone(), dpms: state.dpms };
+    if let Err(err) = fs::write(path, serde_json::to_string(&saved).unwrap_or_default()) {
+        warn!("Couldn't save the state to {}: {}", path.display(), err);
+    }
+}
+
+pub(crate) fn remove_state_file(state: &mut State) {
+    if let Some(path) = state.state_file.take() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionType;
+
+    #[test]
+    fn the_last_instance_hands_its_state_over() {
+        let path = env::temp_dir().join(format!("attention-{}.state", process::id()));
+        let mut state = State::new(SessionType::X11);
+        state.state_file = Some(path.clone());
+        state.app_name = "mpv".to_owned();
+        state.last_screen_blanking_state = ScreenBlankingState::Off;
+        save(&state);
+
+        let saved = load(&path).unwrap();
+        assert!(saved.blanking == ScreenBlankingState::Off);
+        assert_eq!(saved.app, "mpv");
+
+        remove_state_file(&mut state);
+        assert!(load(&path).is_none());
+    }
+}
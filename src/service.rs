@@ -0,0 +1,124 @@
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+#[cfg(target_os = "linux")]
+use std::os::linux::net::SocketAddrExt;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(unix)]
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::path::Path;
+use std::process;
+
+use log::debug;
+
+use crate::config::{config_dir, Config};
+use crate::error::AttentionError;
+
+// One template unit serves every profile, `attention@<profile>.service`
+// passes the instance name on as --profile.
+pub(crate) fn unit(exe: &Path) -> String {
+    let exe = exe.display().to_string();
+    let exe = if exe.contains(char::is_whitespace) { format!("\"{}\"", exe) } else { exe };
+    format!("\
+[Unit]
+Description=Keep the screen on for the attention profile %i
+PartOf=graphical-session.target
+After=graphical-session.target
+
+[Service]
+Type=notify
+ExecStart={} --profile %i
+Restart=on-failure
+WatchdogSec=30
+
+[Install]
+WantedBy=graphical-session.target
+", exe)
+}
+
+pub(crate) fn install_service(profile: &str) -> Result<(), AttentionError> {
+    // A typo'd profile would only show up once the unit fails to start.
+    Config::load()?.profile(profile)?;
+
+    let install_error = |path: &Path, source| AttentionError::Install { path: path.to_owned(), source };
+    let exe = env::current_exe().map_err(|source| install_error(Path::new("attention"), source))?;
+    let dir = config_dir()
+    .ok_or_else(|| install_error(Path::new("~/.config"), io::Error::new(io::ErrorKind::NotFound, "neither XDG_CONFIG_HOME nor HOME is set")))?
+    .join("systemd")
+    .join("user");
+    let path = dir.join("attention@.service");
+    fs::create_dir_all(&dir).map_err(|source| install_error(&dir, source))?;
+    fs::write(&path, unit(&exe)).map_err(|source| install_error(&path, source))?;
+
+    println!("Wrote {}, start it with the session using:", path.display());
+    println!("  systemctl --user daemon-reload");
+    println!("  systemctl --user enable --now attention@{}.service", profile);
+    Ok(())
+}
+
+// Tells systemd how we're doing when it started us with Type=notify, and
+// does nothing otherwise. A lost message only costs readiness or one ping.
+pub(crate) fn sd_notify(message: &str) {
+    let Some(socket) = env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    if let Err(err) = send_notify(&socket, message) {
+        debug!("Couldn't tell systemd {}: {}", message, err);
+    }
+}
+
+// A socket starting with @ is in the abstract namespace.
+#[cfg(unix)]
+fn send_notify(socket: &OsStr, message: &str) -> io::Result<()> {
+    let datagram = UnixDatagram::unbound()?;
+    let address = match socket.as_bytes().strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        _ => SocketAddr::from_pathname(socket)?
+    };
+    datagram.send_to_addr(message.as_bytes(), &address)?;
+    Ok(())
+}
+
+// systemd only runs where there are unix sockets to tell it things on.
+#[cfg(not(unix))]
+fn send_notify(_socket: &OsStr, _message: &str) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+// With WatchdogSec= systemd restarts us once the loop stops pinging, say
+// because a helper command hangs in a way the timeouts don't catch.
+pub(crate) fn watchdog() {
+    let ours = env::var("WATCHDOG_PID").map_or(true, |pid| pid == process::id().to_string());
+    if ours && env::var_os("WATCHDOG_USEC").is_some() {
+        sd_notify("WATCHDOG=1");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notifications_reach_the_socket() {
+        let path = env::temp_dir().join(format!("attention-notify-{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let socket = UnixDatagram::bind(&path).unwrap();
+        send_notify(path.as_os_str(), "READY=1").unwrap();
+        let mut buf = [0; 16];
+        let len = socket.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"READY=1");
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn the_unit_runs_this_binary_with_the_instance_profile() {
+        let installed = unit(Path::new("/usr/local/bin/attention"));
+        assert!(installed.contains("\nExecStart=/usr/local/bin/attention --profile %i\n"));
+        assert!(installed.contains("\nWantedBy=graphical-session.target\n"));
+        assert!(installed.contains("\nType=notify\n"));
+        assert!(unit(Path::new("/home/me/my bin/attention")).contains("ExecStart=\"/home/me/my bin/attention\" --profile %i"));
+    }
+}
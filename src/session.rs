@@ -0,0 +1,160 @@
+use std::env;
+
+use crate::audio::AudioBackend;
+use crate::condition::Signal;
+use crate::error::AttentionError;
+use crate::inhibit::InhibitMethod;
+use crate::options::Options;
+use crate::runner::in_path;
+use crate::state::State;
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum SessionType {
+    X11,
+    Sway,
+    Hyprland
+}
+
+// Hyprland and sway win over X11 when both are around, DISPLAY is usually just XWayland
+// there and `xset` wouldn't keep the outputs on. Whether the tools are
+// installed is left to `check_dependencies`.
+// A --display given on the command line is X11 whatever the environment says.
+pub(crate) fn detect_session(display: Option<&str>) -> Result<SessionType, AttentionError> {
+    let has_env = |name: &str| env::var_os(name).is_some_and(|value| !value.is_empty());
+
+    if display.is_some() {
+        return Ok(SessionType::X11);
+    }
+    if has_env("HYPRLAND_INSTANCE_SIGNATURE") {
+        return Ok(SessionType::Hyprland);
+    }
+    if has_env("WAYLAND_DISPLAY") && has_env("SWAYSOCK") {
+        return Ok(SessionType::Sway);
+    }
+    if has_env("DISPLAY") {
+        return Ok(SessionType::X11);
+    }
+    Err(AttentionError::NoSession)
+}
+
+// Everything the chosen session and backends will shell out to, paired with
+// what it's needed for.
+pub(crate) fn required_commands(options: &Options, state: &State) -> Vec<(&'static str, &'static str)> {
+    let condition = options.condition();
+    let uses = |signal| condition.as_ref().is_some_and(|condition| condition.uses(signal));
+    let mut required = Vec::new();
+    // A custom inhibitor replaces xset, and notify-send is only asked for
+    // when it's there.
+    if state.notify && state.inhibitor.is_none() {
+        required.push(("notify-send", "for notifications"));
+    }
+    match state.session {
+        SessionType::X11 => {
+            if !options.timed() {
+                required.push(("wmctrl", "to find the app's window"));
+            }
+            if uses(Signal::Fullscreen) {
+                required.push(("xprop", "to track fullscreen"));
+            } else if options.focused_only || uses(Signal::Focused) {
+                required.push(("xprop", "for --focused-only"));
+            } else if options.match_class.is_some() {
+                required.push(("xprop", "for --match-class"));
+            }
+            if options.output.is_some() {
+                required.push(("xwininfo", "for --output"));
+                required.push(("xrandr", "for --output"));
+            }
+            match (state.inhibitor.is_some(), state.inhibit_method) {
+                (false, InhibitMethod::Xset) => required.push(("xset", "to turn off screen blanking")),
+                (false, InhibitMethod::Reset) => required.push(("xset", "to reset the idle timer")),
+                _ => {}
+            }
+        }
+        _ if options.timed() => {}
+        SessionType::Sway => required.push(("swaymsg", "to find the app's window")),
+        SessionType::Hyprland => required.push(("hyprctl", "to find the app's window"))
+    }
+    if state.inhibitor.is_none() && state.inhibit_method == InhibitMethod::XdgScreensaver {
+        required.push(("xdg-screensaver", "to suspend the screensaver"));
+    }
+    if options.idle_release.is_some() || uses(Signal::Idle) {
+        required.push(("xprintidle", "to tell how long you've been idle"));
+    }
+    if state.kill_on_exit {
+        required.push(("kill", "for --kill-on-exit"));
+    }
+    if uses(Signal::Audio) {
+        match state.audio_backend {
+            AudioBackend::Pipewire => required.push(("pw-dump", "to track audio")),
+            AudioBackend::Subscribe | AudioBackend::Pactl | AudioBackend::Auto => required.push(("pactl", "to track audio"))
+        }
+    }
+    required
+}
+
+// Runs before anything is inhibited so a missing tool shows up as one list
+// up front instead of a failure halfway through.
+pub(crate) fn check_dependencies(options: &Options, state: &State) -> Result<(), AttentionError> {
+    let missing: Vec<String> = required_commands(options, state)
+    .into_iter()
+    .filter(|(cmd, _)| !in_path(cmd))
+    .map(|(cmd, purpose)| format!("{} ({})", cmd, purpose))
+    .collect();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(AttentionError::MissingCommands(missing))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::inhibitor::Inhibitor;
+
+    fn commands(args: &[&str], session: SessionType, audio_backend: AudioBackend) -> Vec<&'static str> {
+        let options = Options::parse_from(args);
+        let mut state = State::new(session);
+        state.inhibit_method = options.inhibit_method;
+        state.notify = !options.no_notify;
+        state.audio_backend = audio_backend;
+        required_commands(&options, &state).into_iter().map(|(cmd, _)| cmd).collect()
+    }
+
+    #[test]
+    fn required_commands_follow_the_enabled_trackers() {
+        assert_eq!(commands(&["attention", "--track-audio", "mpv"], SessionType::X11, AudioBackend::Pactl),
+            vec!["notify-send", "wmctrl", "xset", "pactl"]);
+        assert_eq!(commands(&["attention", "--track-fullscreen", "--inhibit-method", "dbus", "--no-notify", "mpv"], SessionType::X11, AudioBackend::Pactl),
+            vec!["wmctrl", "xprop"]);
+        assert_eq!(commands(&["attention", "--track-audio", "--track-fullscreen", "mpv"], SessionType::Sway, AudioBackend::Pipewire),
+            vec!["notify-send", "swaymsg", "pw-dump"]);
+        assert_eq!(commands(&["attention", "--track-fullscreen", "--inhibit-method", "dbus", "mpv"], SessionType::Hyprland, AudioBackend::Pactl),
+            vec!["notify-send", "hyprctl"]);
+        assert_eq!(commands(&["attention", "--condition", "focused && idle<60s", "--no-notify", "mpv"], SessionType::X11, AudioBackend::Pactl),
+            vec!["wmctrl", "xprop", "xset", "xprintidle"]);
+    }
+
+    struct NoopInhibitor;
+
+    impl Inhibitor for NoopInhibitor {
+        fn inhibit(&mut self, _app_name: &str) -> Result<(), AttentionError> {
+            Ok(())
+        }
+
+        fn release(&mut self) -> Result<(), AttentionError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn a_custom_inhibitor_needs_neither_xset_nor_notify_send() {
+        let options = Options::parse_from(["attention", "--for", "10m"]);
+        let mut state = State::new(SessionType::X11);
+        state.inhibitor = Some(Box::new(NoopInhibitor));
+        assert!(required_commands(&options, &state).is_empty());
+    }
+}
@@ -0,0 +1,303 @@
+use core::time;
+use std::collections::HashSet;
+use std::fs::File;
+use std::mem;
+use std::path::PathBuf;
+use std::process::Child;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use zbus::blocking::Connection;
+
+use crate::audio::{AudioBackend, StreamFilter};
+use crate::inhibit::{DpmsSettings, InhibitMethod, InhibitScope, LogindLock, ScopeInhibits, Urgency, DEFAULT_OFF_TEXT, DEFAULT_ON_TEXT, DEFAULT_REASON_TEXT};
+use crate::inhibitor::Inhibitor;
+use crate::metrics::Metrics;
+use crate::runner::SystemRunner;
+use crate::session::SessionType;
+use crate::window::TrackedWindow;
+
+#[derive(PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScreenBlankingState {
+    Off,
+    On
+}
+
+#[derive(PartialEq, Eq)]
+pub enum FullscreenState {
+    NotFullscreen,
+    Fullscreen
+}
+
+#[derive(PartialEq, Eq)]
+pub enum TrackAudioState {
+    On,
+    Off
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InhibitReason {
+    Audio,
+    Fullscreen,
+    // Asked for through `Attention::inhibit` rather than caused by the app.
+    Manual,
+    // The --condition expression holds.
+    Condition,
+    // Blanking was off when the last instance went away, held until the
+    // trackers have had their first look.
+    Restored,
+    // --inhibit-on-start, held the same way.
+    Start,
+    // An MPRIS player of the app's says it's playing, with --track-mpris.
+    Mpris
+}
+
+impl InhibitReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InhibitReason::Audio => "audio",
+            InhibitReason::Fullscreen => "fullscreen",
+            InhibitReason::Manual => "manual",
+            InhibitReason::Condition => "condition",
+            InhibitReason::Restored => "restored",
+            InhibitReason::Start => "start",
+            InhibitReason::Mpris => "mpris"
+        }
+    }
+}
+
+// What each app tracked with --apps keeps to itself. The one being looked at
+// lives in the fields of `State`, its slot in `State::apps` is left empty
+// until `switch_app` moves on to another.
+pub struct AppState {
+    pub app_name: String,
+    pub window: Option<TrackedWindow>,
+    pub last_fullscreen_state: FullscreenState,
+    pub last_track_audio_state: TrackAudioState,
+    pub mpris_playing: bool,
+    pub silence_since: Option<Instant>,
+    pub window_gone_since: Option<Instant>,
+    pub window_stale_since: Option<Instant>,
+    pub windows_listed_at: Option<Instant>,
+    pub fullscreen_pending: u32,
+    pub inhibit_reasons: HashSet<InhibitReason>
+}
+
+impl AppState {
+    pub fn new(app_name: String, window: TrackedWindow) -> Self {
+        Self {
+            app_name,
+            window: Some(window),
+            last_fullscreen_state: FullscreenState::NotFullscreen,
+            last_track_audio_state: TrackAudioState::Off,
+            mpris_playing: false,
+            silence_since: None,
+            window_gone_since: None,
+            window_stale_since: None,
+            windows_listed_at: None,
+            fullscreen_pending: 0,
+            inhibit_reasons: HashSet::new()
+        }
+    }
+}
+
+pub struct State {
+    pub last_screen_blanking_state: ScreenBlankingState,
+    pub last_fullscreen_state: FullscreenState,
+    pub last_track_audio_state: TrackAudioState,
+    pub mpris_playing: bool,
+    pub inhibit_reasons: HashSet<InhibitReason>,
+    pub session: SessionType,
+    pub window: Option<TrackedWindow>,
+    pub child: Option<Child>,
+    pub kill_on_exit: bool,
+    pub inhibit_method: InhibitMethod,
+    pub inhibitor: Option<Box<dyn Inhibitor + Send>>,
+    pub audio_backend: AudioBackend,
+    pub stream_filter: StreamFilter,
+    pub dbus: Option<Connection>,
+    pub dbus_cookie: Option<u32>,
+    pub mpris: Option<Connection>,
+    pub logind_lock: Option<LogindLock>,
+    pub xdg_suspended: Option<String>,
+    pub inhibit_scope: InhibitScope,
+    pub scope_inhibits: ScopeInhibits,
+    pub dpms: Option<DpmsSettings>,
+    pub silence_since: Option<Instant>,
+    pub startup_grace: time::Duration,
+    pub window_gone_grace: time::Duration,
+    pub window_gone_since: Option<Instant>,
+    pub window_stale_since: Option<Instant>,
+    pub windows_listed_at: Option<Instant>,
+    pub window_shown_at: Option<Instant>,
+    pub warmup_ignore_audio: time::Duration,
+    pub audio_ignored_until: Option<Instant>,
+    pub idle: bool,
+    pub unfocused: bool,
+    pub unfocused_since: Option<Instant>,
+    pub fullscreen_confirm: u32,
+    pub debug_dump: Option<time::Duration>,
+    pub dumped_at: Option<Instant>,
+    pub output: Option<String>,
+    pub fullscreen_pending: u32,
+    pub off_hours: bool,
+    pub on_battery: bool,
+    pub manual_override: bool,
+    pub app_name: String,
+    pub json: bool,
+    pub dry_run: bool,
+    pub notify: bool,
+    pub notify_on_text: String,
+    pub notify_off_text: String,
+    pub reason: String,
+    pub notify_icon: Option<String>,
+    pub notify_urgency: Option<Urgency>,
+    pub notify_cooldown: time::Duration,
+    pub notify_timeout: Option<time::Duration>,
+    pub notify_replace: bool,
+    pub notification_id: Option<u32>,
+    pub notified_on_at: Option<Instant>,
+    pub notified_off_at: Option<Instant>,
+    pub on_inhibit: Option<String>,
+    pub on_release: Option<String>,
+    pub socket: Option<PathBuf>,
+    pub state_file: Option<PathBuf>,
+    pub pidfile: Option<PathBuf>,
+    pub metrics: Metrics,
+    // --once hands blanking over to the next run instead of restoring it.
+    pub keep_on_exit: bool,
+    // Held until we exit, see instance.rs.
+    pub instance_lock: Option<File>,
+    // What the signal handler and the guard restore blanking with.
+    pub runner: SystemRunner,
+    pub apps: Vec<AppState>,
+    pub current_app: usize
+}
+
+impl State {
+    pub fn new(session: SessionType) -> Self {
+        Self {
+            last_screen_blanking_state: ScreenBlankingState::On,
+            last_fullscreen_state: FullscreenState::NotFullscreen,
+            last_track_audio_state: TrackAudioState::Off,
+            mpris_playing: false,
+            inhibit_reasons: HashSet::new(),
+            session,
+            window: None,
+            child: None,
+            kill_on_exit: false,
+            inhibit_method: InhibitMethod::Xset,
+            inhibitor: None,
+            audio_backend: AudioBackend::Pactl,
+            stream_filter: StreamFilter::default(),
+            dbus: None,
+            dbus_cookie: None,
+            mpris: None,
+            logind_lock: None,
+            xdg_suspended: None,
+            inhibit_scope: InhibitScope::Display,
+            scope_inhibits: ScopeInhibits::default(),
+            dpms: None,
+            silence_since: None,
+            idle: false,
+            unfocused: false,
+            unfocused_since: None,
+            fullscreen_confirm: 1,
+            debug_dump: None,
+            dumped_at: None,
+            output: None,
+            fullscreen_pending: 0,
+            off_hours: false,
+            on_battery: false,
+            manual_override: false,
+            app_name: String::new(),
+            json: false,
+            dry_run: false,
+            notify: true,
+            notify_on_text: DEFAULT_ON_TEXT.to_owned(),
+            reason: DEFAULT_REASON_TEXT.to_owned(),
+            notify_off_text: DEFAULT_OFF_TEXT.to_owned(),
+            notify_icon: None,
+            notify_urgency: None,
+            notify_cooldown: time::Duration::ZERO,
+            notify_timeout: None,
+            notify_replace: false,
+            notification_id: None,
+            notified_on_at: None,
+            notified_off_at: None,
+            on_inhibit: None,
+            on_release: None,
+            socket: None,
+            state_file: None,
+            pidfile: None,
+            metrics: Metrics::default(),
+            keep_on_exit: false,
+            startup_grace: time::Duration::ZERO,
+            window_gone_grace: time::Duration::ZERO,
+            window_gone_since: None,
+            window_stale_since: None,
+            windows_listed_at: None,
+            window_shown_at: None,
+            warmup_ignore_audio: time::Duration::ZERO,
+            audio_ignored_until: None,
+            instance_lock: None,
+            runner: SystemRunner::default(),
+            apps: Vec::new(),
+            current_app: 0
+        }
+    }
+
+    // --startup-grace and --warmup-ignore-audio count from when the window
+    // showed up.
+    pub(crate) fn window_shown(&mut self) {
+        let now = Instant::now();
+        self.window_shown_at = Some(now);
+        self.audio_ignored_until = Some(now + self.warmup_ignore_audio);
+    }
+
+    pub(crate) fn track_apps(&mut self, apps: Vec<AppState>) {
+        self.apps = apps;
+        self.current_app = 0;
+        self.swap_app(0);
+    }
+
+    // Puts the current app back in its slot and takes out the one at `index`.
+    pub(crate) fn switch_app(&mut self, index: usize) {
+        if index != self.current_app {
+            self.swap_app(self.current_app);
+            self.swap_app(index);
+            self.current_app = index;
+        }
+    }
+
+    fn swap_app(&mut self, index: usize) {
+        let app = &mut self.apps[index];
+        mem::swap(&mut self.app_name, &mut app.app_name);
+        mem::swap(&mut self.window, &mut app.window);
+        mem::swap(&mut self.last_fullscreen_state, &mut app.last_fullscreen_state);
+        mem::swap(&mut self.last_track_audio_state, &mut app.last_track_audio_state);
+        mem::swap(&mut self.mpris_playing, &mut app.mpris_playing);
+        mem::swap(&mut self.silence_since, &mut app.silence_since);
+        mem::swap(&mut self.window_gone_since, &mut app.window_gone_since);
+        mem::swap(&mut self.window_stale_since, &mut app.window_stale_since);
+        mem::swap(&mut self.windows_listed_at, &mut app.windows_listed_at);
+        mem::swap(&mut self.fullscreen_pending, &mut app.fullscreen_pending);
+        mem::swap(&mut self.inhibit_reasons, &mut app.inhibit_reasons);
+    }
+
+    // Why the apps other than the current one want the screen on.
+    pub(crate) fn reasons_elsewhere(&self) -> impl Iterator<Item = InhibitReason> + '_ {
+        self.apps.iter()
+        .enumerate()
+        .filter(|&(index, _)| index != self.current_app)
+        .flat_map(|(_, app)| app.inhibit_reasons.iter().copied())
+    }
+}
+
+// A panic while holding the lock shouldn't stop us from restoring blanking.
+pub(crate) fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,72 @@
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use chrono::Local;
+use env_logger::fmt::Formatter;
+use log::{Level, Record};
+
+#[derive(Clone, Copy)]
+pub(crate) enum Color {
+    Red,
+    Green,
+    Yellow
+}
+
+impl Color {
+    fn code(self) -> u8 {
+        match self {
+            Color::Red => 31,
+            Color::Green => 32,
+            Color::Yellow => 33
+        }
+    }
+}
+
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+// The log goes to stderr, colors only when that's a terminal and NO_COLOR
+// isn't set, piping it anywhere gets the plain text.
+fn colors() -> bool {
+    static COLORS: OnceLock<bool> = OnceLock::new();
+    !PLAIN.load(Ordering::Relaxed) && *COLORS.get_or_init(|| io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none())
+}
+
+// For when the log goes somewhere other than stderr.
+pub(crate) fn plain_text() {
+    PLAIN.store(true, Ordering::Relaxed);
+}
+
+fn paint_if(colors: bool, color: Color, text: &str) -> String {
+    if colors {
+        format!("\x1b[{}m{}\x1b[0m", color.code(), text)
+    } else {
+        text.to_owned()
+    }
+}
+
+pub(crate) fn paint(color: Color, text: &str) -> String {
+    paint_if(colors(), color, text)
+}
+
+// `12:00:03 Turning off screen blanking..`, anything but info says what it is.
+pub fn format_log(buf: &mut Formatter, record: &Record) -> io::Result<()> {
+    let time = Local::now().format("%H:%M:%S");
+    match record.level() {
+        Level::Info => writeln!(buf, "{} {}", time, record.args()),
+        Level::Error => writeln!(buf, "{} {}", time, paint(Color::Red, &format!("ERROR {}", record.args()))),
+        level => writeln!(buf, "{} {:<5} {}", time, level, record.args())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn painting_is_left_out_without_a_terminal() {
+        assert_eq!(paint_if(true, Color::Green, "Turning off screen blanking.."), "\x1b[32mTurning off screen blanking..\x1b[0m");
+        assert_eq!(paint_if(false, Color::Green, "Turning off screen blanking.."), "Turning off screen blanking..");
+    }
+}
@@ -0,0 +1,702 @@
+use core::time;
+use std::iter;
+use std::panic;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use chrono::NaiveTime;
+
+use crate::audio::{is_playing_audio, AudioBackend, StreamFilter};
+use crate::condition::{Condition, Signal, Signals};
+use crate::error::AttentionError;
+use crate::event::{report, Event};
+use crate::mpris::is_mpris_playing;
+use crate::inhibit::{release_screen_blanking, turn_off_screen_blanking, turn_on_screen_blanking};
+use crate::options::ActiveHours;
+use crate::output::window_on_output;
+use crate::procfs::process_tree;
+use crate::runner::{run_command, with_retries, CommandRunner};
+use crate::session::SessionType;
+use crate::state::{FullscreenState, InhibitReason, ScreenBlankingState, State, TrackAudioState};
+use crate::window::{find_tracked_window, is_app_fullscreen, is_window_focused, is_window_fullscreen, refresh_window, window_stale, TrackedWindow};
+
+pub(crate) fn we_are_tracking_fullscreen(runner: &dyn CommandRunner, app_name: &str, window: &mut TrackedWindow, state: &mut State) -> Result<(), AttentionError> {
+    let reading = is_app_fullscreen(runner, state.session, window);
+    match fullscreen_reading(runner, reading, window, state)? {
+        Some(fullscreen) => fullscreen_changed(runner, app_name, fullscreen, state),
+        None => Ok(())
+    }
+}
+
+// None while the window's id has gone stale and a new one hasn't turned up,
+// `window` is swapped for the new one once it does.
+pub(crate) fn fullscreen_reading(runner: &dyn CommandRunner, reading: Result<bool, AttentionError>, window: &mut TrackedWindow, state: &mut State) -> Result<Option<bool>, AttentionError> {
+    match reading {
+        Ok(fullscreen) => {
+            state.window_stale_since = None;
+            Ok(Some(fullscreen))
+        }
+        Err(err) if window_stale(&err) => {
+            if let Some(refreshed) = refresh_window(runner, window, err, state)? {
+                *window = refreshed;
+            }
+            Ok(None)
+        }
+        Err(err) => Err(err)
+    }
+}
+
+// With --output fullscreen only counts on that output. With several windows
+// it's one of the fullscreen ones that has to be there.
+fn fullscreen_here(runner: &dyn CommandRunner, window: &TrackedWindow, state: &State) -> Result<bool, AttentionError> {
+    let Some(output) = &state.output else {
+        return Ok(true);
+    };
+    if window.others.is_empty() {
+        return window_on_output(runner, &window.id, output);
+    }
+    Ok(iter::once(&window.id)
+    .chain(&window.others)
+    .any(|id| is_window_fullscreen(runner, state.session, id).unwrap_or(false) && window_on_output(runner, id, output).unwrap_or(false)))
+}
+
+// Shared by polling and `xprop -spy`, only acts when the state flips.
+pub(crate) fn fullscreen_changed(runner: &dyn CommandRunner, app_name: &str, fullscreen: bool, state: &mut State) -> Result<(), AttentionError> {
+    let fullscreen = match &state.window {
+        Some(window) if fullscreen => fullscreen_here(runner, window, state)?,
+        _ => fullscreen
+    };
+    // Compositors can drop fullscreen for a moment while switching
+    // workspaces, a flip has to be seen `fullscreen_confirm` times in a row.
+    let flipped = fullscreen != (state.last_fullscreen_state == FullscreenState::Fullscreen);
+    if !flipped {
+        state.fullscreen_pending = 0;
+        return Ok(());
+    }
+    state.fullscreen_pending += 1;
+    if state.fullscreen_pending < state.fullscreen_confirm {
+        return Ok(());
+    }
+    state.fullscreen_pending = 0;
+
+    if fullscreen {
+        if state.last_fullscreen_state == FullscreenState::NotFullscreen {
+            report(state.json, Event::FullscreenEntered { app: app_name });
+            state.last_fullscreen_state = FullscreenState::Fullscreen;
+            turn_off_screen_blanking(runner, app_name, InhibitReason::Fullscreen, state)?;
+        }
+    } else if state.last_fullscreen_state == FullscreenState::Fullscreen {
+        report(state.json, Event::FullscreenLeft { app: app_name });
+        state.last_fullscreen_state = FullscreenState::NotFullscreen;
+        release_screen_blanking(runner, InhibitReason::Fullscreen, state)?;
+    }
+    Ok(())
+}
+
+// Audio only counts as stopped once it's been silent for `grace`, so gaps
+// between videos or buffering don't flip blanking back and forth.
+pub(crate) fn we_are_tracking_audio(runner: &dyn CommandRunner, app_name: &str, pid: u32, grace: time::Duration, state: &mut State) -> Result<(), AttentionError> {
+    let name = state.window.as_ref().and_then(TrackedWindow::stream_regex).cloned();
+    let playing = is_playing_audio(runner, state.audio_backend, &process_tree(pid), name.as_ref(), &state.stream_filter)?;
+    audio_changed(runner, app_name, playing, grace, state)
+}
+
+// Shared by polling and `pactl subscribe`, has to run every tick even when
+// nothing changed so the grace period can run out.
+// Audio in the --warmup-ignore-audio seconds after the window showed up is
+// taken for silence.
+pub(crate) fn audio_changed(runner: &dyn CommandRunner, app_name: &str, playing: bool, grace: time::Duration, state: &mut State) -> Result<(), AttentionError> {
+    let playing = playing && state.audio_ignored_until.is_none_or(|until| Instant::now() >= until);
+    if playing {
+        state.silence_since = None;
+        if state.last_track_audio_state == TrackAudioState::Off {
+            report(state.json, Event::AudioStarted { app: app_name });
+            state.last_track_audio_state = TrackAudioState::On;
+            turn_off_screen_blanking(runner, app_name, InhibitReason::Audio, state)?;
+        }
+    } else if state.last_track_audio_state == TrackAudioState::On {
+        let silence_since = *state.silence_since.get_or_insert_with(Instant::now);
+        if silence_since.elapsed() >= grace {
+            report(state.json, Event::AudioStopped { app: app_name });
+            state.last_track_audio_state = TrackAudioState::Off;
+            state.silence_since = None;
+            release_screen_blanking(runner, InhibitReason::Audio, state)?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn we_are_tracking_mpris(runner: &dyn CommandRunner, app_name: &str, pid: u32, state: &mut State) -> Result<(), AttentionError> {
+    let playing = is_mpris_playing(&process_tree(pid), app_name, state)?;
+    mpris_changed(runner, app_name, playing, state)
+}
+
+pub(crate) fn mpris_changed(runner: &dyn CommandRunner, app_name: &str, playing: bool, state: &mut State) -> Result<(), AttentionError> {
+    if playing == state.mpris_playing {
+        return Ok(());
+    }
+    state.mpris_playing = playing;
+    if playing {
+        report(state.json, Event::PlaybackStarted { app: app_name });
+        turn_off_screen_blanking(runner, app_name, InhibitReason::Mpris, state)
+    } else {
+        report(state.json, Event::PlaybackStopped { app: app_name });
+        release_screen_blanking(runner, InhibitReason::Mpris, state)
+    }
+}
+
+pub(crate) fn idle_time(runner: &dyn CommandRunner) -> Result<time::Duration, AttentionError> {
+    let output = run_command(runner, "xprintidle", &[])?;
+
+    String::from_utf8_lossy(&output.stdout).trim().parse()
+    .map(time::Duration::from_millis)
+    .map_err(|_| AttentionError::UnexpectedOutput { cmd: "xprintidle".to_owned(), reason: "expected idle milliseconds".to_owned() })
+}
+
+// Gives blanking back while the reasons to keep it off are kept, so they can
+// take effect again once whatever suspended them is over.
+pub(crate) fn suspend_inhibit(runner: &dyn CommandRunner, state: &mut State) -> Result<(), AttentionError> {
+    if state.last_screen_blanking_state == ScreenBlankingState::Off {
+        let reasons = state.inhibit_reasons.clone();
+        turn_on_screen_blanking(runner, state)?;
+        state.inhibit_reasons = reasons;
+    }
+    Ok(())
+}
+
+pub(crate) fn resume_inhibit(runner: &dyn CommandRunner, app_name: &str, state: &mut State) -> Result<(), AttentionError> {
+    // Another app's reason is only borrowed to turn blanking off, it stays
+    // that app's to release.
+    let own = state.inhibit_reasons.iter().next().copied();
+    if let Some(reason) = own.or_else(|| state.reasons_elsewhere().next()) {
+        turn_off_screen_blanking(runner, app_name, reason, state)?;
+        if own.is_none() {
+            state.inhibit_reasons.remove(&reason);
+        }
+    }
+    Ok(())
+}
+
+// With --idle-release someone who walked away gets their screen blanking back
+// even though the app still wants it off, coming back turns it off again
+// without waiting for a new transition.
+pub(crate) fn we_are_tracking_idle(runner: &dyn CommandRunner, app_name: &str, threshold: time::Duration, state: &mut State) -> Result<(), AttentionError> {
+    let idle = idle_time(runner)?;
+    if idle >= threshold && !state.idle {
+        report(state.json, Event::UserIdle { app: app_name, idle_secs: idle.as_secs() });
+        state.idle = true;
+        suspend_inhibit(runner, state)?;
+    } else if idle < threshold && state.idle {
+        report(state.json, Event::UserBack { app: app_name });
+        state.idle = false;
+        resume_inhibit(runner, app_name, state)?;
+    }
+    Ok(())
+}
+
+// With --focused-only the app only keeps the screen on while it has focus,
+// losing it counts after `grace` like silence does.
+pub(crate) fn focus_changed(runner: &dyn CommandRunner, app_name: &str, focused: bool, grace: time::Duration, state: &mut State) -> Result<(), AttentionError> {
+    if focused {
+        state.unfocused_since = None;
+        if state.unfocused {
+            report(state.json, Event::FocusGained { app: app_name });
+            state.unfocused = false;
+            resume_inhibit(runner, app_name, state)?;
+        }
+    } else if !state.unfocused {
+        let unfocused_since = *state.unfocused_since.get_or_insert_with(Instant::now);
+        if unfocused_since.elapsed() >= grace {
+            report(state.json, Event::FocusLost { app: app_name });
+            state.unfocused = true;
+            state.unfocused_since = None;
+            suspend_inhibit(runner, state)?;
+        }
+    }
+    Ok(())
+}
+
+// `now` is passed in so this doesn't depend on the clock in tests.
+pub(crate) fn we_are_tracking_hours(runner: &dyn CommandRunner, app_name: &str, hours: ActiveHours, now: NaiveTime, state: &mut State) -> Result<(), AttentionError> {
+    let active = hours.contains(now);
+    if !active && !state.off_hours {
+        report(state.json, Event::HoursEnded { app: app_name });
+        state.off_hours = true;
+        suspend_inhibit(runner, state)?;
+    } else if active && state.off_hours {
+        report(state.json, Event::HoursStarted { app: app_name });
+        state.off_hours = false;
+        resume_inhibit(runner, app_name, state)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn we_are_tracking_power(runner: &dyn CommandRunner, app_name: &str, on_ac: bool, state: &mut State) -> Result<(), AttentionError> {
+    if !on_ac && !state.on_battery {
+        report(state.json, Event::Unplugged { app: app_name });
+        state.on_battery = true;
+        suspend_inhibit(runner, state)?;
+    } else if on_ac && state.on_battery {
+        report(state.json, Event::PluggedIn { app: app_name });
+        state.on_battery = false;
+        resume_inhibit(runner, app_name, state)?;
+    }
+    Ok(())
+}
+
+// The shortcut flags go through their own trackers, which know about
+// --grace and the watchers. A --condition is simpler, every signal it
+// mentions is looked up fresh and the whole thing is one reason.
+pub(crate) fn we_are_tracking_condition(runner: &dyn CommandRunner, app_name: &str, condition: &Condition, window: &TrackedWindow, state: &mut State) -> Result<(), AttentionError> {
+    let mut signals = Signals::default();
+    if condition.uses(Signal::Audio) {
+        signals.audio = is_playing_audio(runner, state.audio_backend, &process_tree(window.pid), window.stream_regex(), &state.stream_filter)?;
+    }
+    if condition.uses(Signal::Fullscreen) {
+        signals.fullscreen = is_app_fullscreen(runner, state.session, window)? && fullscreen_here(runner, window, state)?;
+    }
+    if condition.uses(Signal::Focused) {
+        signals.focused = is_window_focused(runner, state.session, &window.id)?;
+    }
+    if condition.uses(Signal::Idle) {
+        signals.idle = idle_time(runner)?;
+    }
+
+    if condition.eval(&signals) {
+        turn_off_screen_blanking(runner, app_name, InhibitReason::Condition, state)
+    } else {
+        release_screen_blanking(runner, InhibitReason::Condition, state)
+    }
+}
+
+// Audio and fullscreen are each polled on their own cadence, the loop wakes
+// for whichever is due first.
+pub(crate) struct Schedule {
+    every: time::Duration,
+    next: Instant
+}
+
+impl Schedule {
+    pub(crate) fn new(every: time::Duration) -> Self {
+        Self { every, next: Instant::now() }
+    }
+
+    // Whether it's time, the next one is pushed back if it is.
+    pub(crate) fn due(&mut self, now: Instant) -> bool {
+        if now < self.next {
+            return false;
+        }
+        self.next = now + self.every;
+        true
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.next = Instant::now();
+    }
+
+    pub(crate) fn until(&self, now: Instant) -> time::Duration {
+        self.next.saturating_duration_since(now)
+    }
+}
+
+pub(crate) struct Wanted {
+    pub audio: bool,
+    pub fullscreen: bool,
+    pub focus: bool
+}
+
+// What one loop got to know about the window, None for whatever wasn't
+// asked or didn't answer in time.
+pub(crate) struct Readings {
+    pub open: Option<Result<bool, AttentionError>>,
+    pub playing: Option<Result<bool, AttentionError>>,
+    pub fullscreen: Option<Result<bool, AttentionError>>,
+    pub focused: Option<Result<bool, AttentionError>>
+}
+
+// A check running on its own thread.
+struct Pending {
+    answer: Receiver<Result<bool, AttentionError>>,
+    handle: JoinHandle<()>
+}
+
+impl Pending {
+    fn spawn(check: impl FnOnce() -> Result<bool, AttentionError> + Send + 'static) -> Self {
+        let (sender, answer) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            let _ = sender.send(check());
+        });
+        Self { answer, handle }
+    }
+}
+
+// Each question gets its own thread and the loop only waits for them until
+// the deadline, so a hung pactl doesn't hold up noticing the window closed.
+// One that misses it is no new reading, the last one stands, and it's left
+// running rather than asked again, its answer is taken on a later loop.
+pub(crate) struct Readers {
+    runner: Arc<dyn CommandRunner + Send + Sync>,
+    session: SessionType,
+    backend: AudioBackend,
+    filter: StreamFilter,
+    retries: u32,
+    open: Option<Pending>,
+    playing: Option<Pending>,
+    fullscreen: Option<Pending>,
+    focused: Option<Pending>
+}
+
+impl Readers {
+    pub(crate) fn new(runner: Arc<dyn CommandRunner + Send + Sync>, session: SessionType, backend: AudioBackend, filter: StreamFilter, retries: u32) -> Self {
+        Self { runner, session, backend, filter, retries, open: None, playing: None, fullscreen: None, focused: None }
+    }
+
+    pub(crate) fn take(&mut self, window: &TrackedWindow, wanted: Wanted, deadline: Instant) -> Readings {
+        let (session, backend, retries) = (self.session, self.backend, self.retries);
+        if wanted.audio && self.playing.is_none() {
+            let (runner, window, filter) = (Arc::clone(&self.runner), window.clone(), self.filter.clone());
+            self.playing = Some(Pending::spawn(move || is_playing_audio(&*runner, backend, &process_tree(window.pid), window.stream_regex(), &filter)));
+        }
+        if wanted.fullscreen && self.fullscreen.is_none() {
+            let (runner, window) = (Arc::clone(&self.runner), window.clone());
+            self.fullscreen = Some(Pending::spawn(move || is_app_fullscreen(&*runner, session, &window)));
+        }
+        if wanted.focus && self.focused.is_none() {
+            let (runner, id) = (Arc::clone(&self.runner), window.id.clone());
+            self.focused = Some(Pending::spawn(move || is_window_focused(&*runner, session, &id)));
+        }
+        if self.open.is_none() {
+            let (runner, window) = (Arc::clone(&self.runner), window.clone());
+            self.open = Some(Pending::spawn(move || with_retries(retries, || find_tracked_window(&*runner, session, &window)).map(|id| id.is_some())));
+        }
+        Readings {
+            open: answer(&mut self.open, deadline),
+            playing: answer(&mut self.playing, deadline),
+            fullscreen: answer(&mut self.fullscreen, deadline),
+            focused: answer(&mut self.focused, deadline)
+        }
+    }
+
+    // The window was swapped, whatever is still running asks about the old one.
+    pub(crate) fn forget(&mut self) {
+        (self.open, self.playing, self.fullscreen, self.focused) = (None, None, None, None);
+    }
+}
+
+fn answer(pending: &mut Option<Pending>, deadline: Instant) -> Option<Result<bool, AttentionError>> {
+    match pending.as_ref()?.answer.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(answer) => {
+            *pending = None;
+            Some(answer)
+        }
+        Err(RecvTimeoutError::Timeout) => None,
+        // The check panicked, that's passed on as if it had run here.
+        Err(RecvTimeoutError::Disconnected) => match pending.take()?.handle.join() {
+            Err(panic) => panic::resume_unwind(panic),
+            Ok(()) => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::runner::MockRunner;
+    use crate::session::SessionType;
+    use crate::state::AppState;
+    use crate::window::mpv_window;
+
+    const PLAYING: &str = "\
+Sink Input #71
+\tCorked: no
+\tProperties:
+\t\tapplication.name = \"mpv\"
+\t\tapplication.process.id = \"9876\"
+";
+
+    const FULLSCREEN: &str = "_NET_WM_STATE(ATOM) = _NET_WM_STATE_FULLSCREEN\n";
+
+    // One poll of audio with pactl answering `pactl`, the commands it ran
+    // are added to `calls`.
+    fn poll_audio(pactl: &str, calls: &mut Vec<String>, state: &mut State) {
+        let runner = MockRunner::new().with("pactl", pactl);
+        we_are_tracking_audio(&runner, "mpv", 9876, time::Duration::ZERO, state).unwrap();
+        calls.extend(runner.calls().into_iter().filter(|call| call != "pactl list sink-inputs"));
+    }
+
+    fn poll_fullscreen(xprop: &str, calls: &mut Vec<String>, state: &mut State) {
+        let runner = MockRunner::new().with("xprop", xprop);
+        we_are_tracking_fullscreen(&runner, "mpv", &mut mpv_window(), state).unwrap();
+        calls.extend(runner.calls().into_iter().filter(|call| !call.starts_with("xprop")));
+    }
+
+    #[test]
+    fn audio_starting_and_stopping_inhibits_once_and_releases_once() {
+        let mut state = State::new(SessionType::X11);
+        let mut calls = Vec::new();
+
+        poll_audio(PLAYING, &mut calls, &mut state);
+        assert!(state.last_track_audio_state == TrackAudioState::On);
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::Off);
+        // Still playing, nothing is run twice.
+        poll_audio(PLAYING, &mut calls, &mut state);
+
+        poll_audio("", &mut calls, &mut state);
+        assert!(state.last_track_audio_state == TrackAudioState::Off);
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::On);
+        poll_audio("", &mut calls, &mut state);
+
+        assert_eq!(calls, vec![
+            "notify-send ⚠️ Power Management is inhibited by mpv (audio)",
+            "xset -dpms",
+            "notify-send ⚠️ Power Management is back to normal",
+            "xset +dpms"
+        ]);
+    }
+
+    #[test]
+    fn blanking_comes_back_only_once_audio_and_fullscreen_both_let_go() {
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        let mut calls = Vec::new();
+
+        poll_audio(PLAYING, &mut calls, &mut state);
+        poll_fullscreen(FULLSCREEN, &mut calls, &mut state);
+        assert!(state.last_fullscreen_state == FullscreenState::Fullscreen);
+        assert!(state.inhibit_reasons == HashSet::from([InhibitReason::Audio, InhibitReason::Fullscreen]));
+
+        poll_audio("", &mut calls, &mut state);
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::Off);
+        poll_fullscreen("_NET_WM_STATE(ATOM) = \n", &mut calls, &mut state);
+        assert!(state.last_fullscreen_state == FullscreenState::NotFullscreen);
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::On);
+        assert!(state.inhibit_reasons.is_empty());
+
+        assert_eq!(calls, vec!["xset -dpms", "xset +dpms"]);
+    }
+
+    #[test]
+    fn a_player_playing_holds_blanking_off_until_it_stops() {
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        mpris_changed(&runner, "mpv", true, &mut state).unwrap();
+        mpris_changed(&runner, "mpv", true, &mut state).unwrap();
+        assert!(state.mpris_playing && state.inhibit_reasons.contains(&InhibitReason::Mpris));
+        mpris_changed(&runner, "mpv", false, &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::On);
+        assert_eq!(runner.calls(), vec!["xset -dpms", "xset +dpms"]);
+    }
+
+    #[test]
+    fn releasing_what_is_already_released_runs_nothing() {
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        release_screen_blanking(&runner, InhibitReason::Audio, &mut state).unwrap();
+        audio_changed(&runner, "mpv", false, time::Duration::ZERO, &mut state).unwrap();
+        fullscreen_changed(&runner, "mpv", false, &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::On);
+        assert!(state.last_track_audio_state == TrackAudioState::Off);
+        assert!(runner.calls().is_empty());
+    }
+
+    #[test]
+    fn a_second_reason_does_not_inhibit_again() {
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        turn_off_screen_blanking(&runner, "mpv", InhibitReason::Audio, &mut state).unwrap();
+        turn_off_screen_blanking(&runner, "mpv", InhibitReason::Fullscreen, &mut state).unwrap();
+        turn_off_screen_blanking(&runner, "mpv", InhibitReason::Audio, &mut state).unwrap();
+        release_screen_blanking(&runner, InhibitReason::Audio, &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::Off);
+        release_screen_blanking(&runner, InhibitReason::Fullscreen, &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::On);
+        assert_eq!(runner.calls(), vec!["xset -dpms", "xset +dpms"]);
+    }
+
+    #[test]
+    fn idling_past_the_threshold_hands_blanking_back_until_the_user_returns() {
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        turn_off_screen_blanking(&MockRunner::new(), "mpv", InhibitReason::Audio, &mut state).unwrap();
+
+        let away = MockRunner::new().with("xprintidle", "600000\n");
+        we_are_tracking_idle(&away, "mpv", time::Duration::from_secs(300), &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::On);
+        assert!(state.inhibit_reasons.contains(&InhibitReason::Audio));
+
+        // Nothing new gets inhibited while idle.
+        turn_off_screen_blanking(&away, "mpv", InhibitReason::Fullscreen, &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::On);
+
+        let back = MockRunner::new().with("xprintidle", "120\n");
+        we_are_tracking_idle(&back, "mpv", time::Duration::from_secs(300), &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::Off);
+        assert_eq!(back.calls(), vec!["xprintidle", "xset -dpms"]);
+    }
+
+    #[test]
+    fn losing_focus_suspends_the_inhibit() {
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        turn_off_screen_blanking(&MockRunner::new(), "mpv", InhibitReason::Fullscreen, &mut state).unwrap();
+
+        let elsewhere = MockRunner::new().with("xprop", "_NET_ACTIVE_WINDOW(WINDOW): window id # 0x2200003\n");
+        let focused = is_window_focused(&elsewhere, SessionType::X11, "0x03a00006").unwrap();
+        focus_changed(&elsewhere, "mpv", focused, time::Duration::ZERO, &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::On);
+
+        let back = MockRunner::new().with("xprop", "_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00006\n");
+        let focused = is_window_focused(&back, SessionType::X11, "0x03a00006").unwrap();
+        focus_changed(&back, "mpv", focused, time::Duration::ZERO, &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::Off);
+    }
+
+    #[test]
+    fn outside_the_active_hours_blanking_is_handed_back() {
+        let at = |hour, min| NaiveTime::from_hms_opt(hour, min, 0).unwrap();
+        let night = ActiveHours { start: at(22, 0), end: at(6, 0) };
+        assert!(night.contains(at(23, 30)) && night.contains(at(1, 0)) && !night.contains(at(6, 0)) && !night.contains(at(12, 0)));
+
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        turn_off_screen_blanking(&runner, "mpv", InhibitReason::Audio, &mut state).unwrap();
+        we_are_tracking_hours(&runner, "mpv", night, at(7, 0), &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::On);
+
+        we_are_tracking_hours(&runner, "mpv", night, at(22, 0), &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::Off);
+        assert_eq!(runner.calls(), vec!["xset -dpms", "xset +dpms", "xset -dpms"]);
+    }
+
+    #[test]
+    fn a_fullscreen_flip_needs_confirming_polls() {
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        state.fullscreen_confirm = 3;
+        fullscreen_changed(&runner, "mpv", true, &mut state).unwrap();
+        fullscreen_changed(&runner, "mpv", true, &mut state).unwrap();
+        // A flicker back resets the count.
+        fullscreen_changed(&runner, "mpv", false, &mut state).unwrap();
+        fullscreen_changed(&runner, "mpv", true, &mut state).unwrap();
+        fullscreen_changed(&runner, "mpv", true, &mut state).unwrap();
+        assert!(state.last_fullscreen_state == FullscreenState::NotFullscreen);
+
+        fullscreen_changed(&runner, "mpv", true, &mut state).unwrap();
+        assert!(state.last_fullscreen_state == FullscreenState::Fullscreen);
+        assert_eq!(runner.calls(), vec!["xset -dpms"]);
+    }
+
+    #[test]
+    fn a_stale_window_id_is_swapped_for_the_new_window() {
+        let runner = MockRunner::new()
+        .with("wmctrl", "0x04c00002  0 9876   desk video.mkv - mpv\n")
+        .failing("xprop", "X Error of failed request:  BadWindow (invalid Window parameter)");
+        let mut state = State::new(SessionType::X11);
+        let mut window = mpv_window();
+        we_are_tracking_fullscreen(&runner, "mpv", &mut window, &mut state).unwrap();
+        assert_eq!(window.id, "0x04c00002");
+        assert_eq!(state.window.as_ref().map(|window| window.id.as_str()), Some("0x04c00002"));
+
+        // Nothing new to take its place, the error stands once the grace is over.
+        let runner = MockRunner::new().failing("xprop", "X Error of failed request:  BadWindow (invalid Window parameter)");
+        assert!(we_are_tracking_fullscreen(&runner, "mpv", &mut window, &mut state).is_err());
+    }
+
+    #[test]
+    fn with_several_apps_blanking_waits_for_the_last_one() {
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        let window = |id: &str| TrackedWindow { id: id.to_owned(), ..mpv_window() };
+        state.track_apps(vec![AppState::new("mpv".to_owned(), window("0x1")), AppState::new("firefox".to_owned(), window("0x2"))]);
+
+        fullscreen_changed(&runner, "mpv", true, &mut state).unwrap();
+        state.switch_app(1);
+        fullscreen_changed(&runner, "firefox", true, &mut state).unwrap();
+        state.switch_app(0);
+        fullscreen_changed(&runner, "mpv", false, &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::Off);
+
+        state.switch_app(1);
+        assert!(state.last_fullscreen_state == FullscreenState::Fullscreen);
+        fullscreen_changed(&runner, "firefox", false, &mut state).unwrap();
+        assert!(state.last_screen_blanking_state == ScreenBlankingState::On);
+        assert_eq!(runner.calls(), vec!["xset -dpms", "xset +dpms"]);
+    }
+
+    #[test]
+    fn audio_right_after_the_window_shows_up_is_ignored() {
+        let runner = MockRunner::new().with("xset", "");
+        let mut state = State::new(SessionType::X11);
+        state.notify = false;
+        state.warmup_ignore_audio = time::Duration::from_secs(60);
+        state.window_shown();
+        audio_changed(&runner, "mpv", true, time::Duration::ZERO, &mut state).unwrap();
+        assert!(state.last_track_audio_state == TrackAudioState::Off);
+
+        state.audio_ignored_until = Some(Instant::now());
+        audio_changed(&runner, "mpv", true, time::Duration::ZERO, &mut state).unwrap();
+        assert!(state.last_track_audio_state == TrackAudioState::On);
+    }
+
+    #[test]
+    fn each_tracker_comes_due_on_its_own_interval() {
+        let mut audio = Schedule::new(time::Duration::from_secs(3));
+        let mut fullscreen = Schedule::new(time::Duration::from_millis(250));
+        let now = Instant::now();
+        assert!(audio.due(now) && fullscreen.due(now));
+        assert!(!audio.due(now) && !fullscreen.due(now));
+        assert_eq!(fullscreen.until(now), time::Duration::from_millis(250));
+
+        let later = now + time::Duration::from_millis(250);
+        assert!(!audio.due(later) && fullscreen.due(later));
+        assert_eq!(audio.until(later), time::Duration::from_millis(2750));
+    }
+
+    fn readers(runner: &Arc<MockRunner>) -> Readers {
+        Readers::new(Arc::clone(runner) as Arc<dyn CommandRunner + Send + Sync>, SessionType::X11, AudioBackend::Pactl, StreamFilter::default(), 0)
+    }
+
+    #[test]
+    fn readings_only_ask_what_is_wanted() {
+        let runner = Arc::new(MockRunner::new()
+        .with("wmctrl", "0x03a00006  0 9876   desk video.mkv - mpv\n")
+        .with("xprop", FULLSCREEN));
+        let wanted = Wanted { audio: false, fullscreen: true, focus: false };
+        let readings = readers(&runner).take(&mpv_window(), wanted, Instant::now() + time::Duration::from_secs(5));
+        assert!(readings.open.unwrap().unwrap());
+        assert!(readings.fullscreen.unwrap().unwrap());
+        assert!(readings.playing.is_none() && readings.focused.is_none());
+
+        let mut calls = runner.calls();
+        calls.sort();
+        assert_eq!(calls, vec!["wmctrl -lp", "xprop -id 0x03a00006"]);
+    }
+
+    #[test]
+    fn a_slow_check_misses_the_deadline_and_answers_later() {
+        let runner = Arc::new(MockRunner::new()
+        .with("wmctrl", "0x03a00006  0 9876   desk video.mkv - mpv\n")
+        .with("xprop", FULLSCREEN)
+        .slow("xprop", time::Duration::from_millis(300)));
+        let mut readers = readers(&runner);
+        let wanted = Wanted { audio: false, fullscreen: true, focus: false };
+        let readings = readers.take(&mpv_window(), wanted, Instant::now() + time::Duration::from_millis(100));
+        assert!(readings.open.unwrap().unwrap());
+        assert!(readings.fullscreen.is_none());
+
+        // Not asked again, the one still running answers.
+        let wanted = Wanted { audio: false, fullscreen: true, focus: false };
+        let readings = readers.take(&mpv_window(), wanted, Instant::now() + time::Duration::from_secs(5));
+        assert!(readings.fullscreen.unwrap().unwrap());
+        assert_eq!(runner.calls().iter().filter(|call| call.starts_with("xprop")).count(), 1);
+    }
+}
@@ -0,0 +1,139 @@
+use core::time;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use crate::window::state_is_fullscreen;
+
+enum Change {
+    Fullscreen(bool),
+    Audio,
+    // Carries the xprop PID, a window that was swapped out can still report
+    // its spy exiting after the new one started.
+    SpyExited(u32),
+    SubscriberExited
+}
+
+// What happened while we were waiting.
+#[derive(Default)]
+pub(crate) struct Changes {
+    pub(crate) fullscreen: Option<bool>,
+    pub(crate) audio: bool
+}
+
+// Long-lived `xprop -spy` and `pactl subscribe` children feeding one channel,
+// so the loop wakes up as soon as either has something to say instead of
+// waiting out the interval. Either one going away means polling again.
+pub(crate) struct Watcher {
+    spy: Option<Child>,
+    subscriber: Option<Child>,
+    sender: Sender<Change>,
+    changes: Receiver<Change>,
+    display: Option<String>
+}
+
+impl Watcher {
+    pub(crate) fn new(display: Option<String>) -> Self {
+        let (sender, changes) = mpsc::channel();
+        Self { spy: None, subscriber: None, sender, changes, display }
+    }
+
+    // `xprop -spy` prints the property right away and then on every change.
+    // Hands back that first state, None when xprop couldn't spy.
+    pub(crate) fn spy_on_fullscreen(&mut self, window_id: &str) -> Option<bool> {
+        let (child, stdout) = spawn("xprop", &["-spy", "-id", window_id, "_NET_WM_STATE"], self.display.as_deref())?;
+        let mut lines = BufReader::new(stdout).lines().map_while(Result::ok);
+        let Some(first) = lines.next() else {
+            reap(child);
+            return None;
+        };
+        let spy_pid = child.id();
+        self.spy.take().into_iter().for_each(reap);
+        self.spy = Some(child);
+
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            for line in lines {
+                if sender.send(Change::Fullscreen(state_is_fullscreen(&line))).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(Change::SpyExited(spy_pid));
+        });
+        Some(state_is_fullscreen(&first))
+    }
+
+    pub(crate) fn subscribe_to_audio(&mut self) {
+        let Some((child, stdout)) = spawn("pactl", &["subscribe"], None) else {
+            return;
+        };
+        self.subscriber = Some(child);
+
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if line.contains(" on sink-input ") && sender.send(Change::Audio).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(Change::SubscriberExited);
+        });
+    }
+
+    pub(crate) fn spying(&self) -> bool {
+        self.spy.is_some()
+    }
+
+    pub(crate) fn subscribed(&self) -> bool {
+        self.subscriber.is_some()
+    }
+
+    // Sleeps for `timeout` unless something changes first.
+    pub(crate) fn wait(&mut self, timeout: time::Duration) -> Changes {
+        let mut changes = Changes::default();
+        let Ok(first) = self.changes.recv_timeout(timeout) else {
+            return changes;
+        };
+        for change in [first].into_iter().chain(self.changes.try_iter().collect::<Vec<_>>()) {
+            match change {
+                Change::Fullscreen(fullscreen) => changes.fullscreen = Some(fullscreen),
+                Change::Audio => changes.audio = true,
+                Change::SpyExited(pid) => {
+                    if self.spy.as_ref().is_some_and(|spy| spy.id() == pid) {
+                        self.spy.take().into_iter().for_each(reap);
+                    }
+                }
+                Change::SubscriberExited => self.subscriber.take().into_iter().for_each(reap)
+            }
+        }
+        changes
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.spy.take().into_iter().chain(self.subscriber.take()).for_each(reap);
+    }
+}
+
+fn spawn(cmd: &str, args: &[&str], display: Option<&str>) -> Option<(Child, ChildStdout)> {
+    let mut command = Command::new(cmd);
+    if let Some(display) = display {
+        command.env("DISPLAY", display);
+    }
+    let mut child = command
+    .args(args)
+    .stdout(Stdio::piped())
+    .stderr(Stdio::null())
+    .spawn()
+    .ok()?;
+    let stdout = child.stdout.take()?;
+    Some((child, stdout))
+}
+
+fn reap(mut child: Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
@@ -0,0 +1,1009 @@
+use core::time;
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::Mutex;
+use std::thread::sleep;
+use std::time::Instant;
+
+use clap::ValueEnum;
+use log::{debug, warn};
+use regex::Regex;
+use serde_json::Value;
+
+use crate::error::AttentionError;
+use crate::event::{report, Event};
+use crate::inhibit::turn_on_screen_blanking;
+use crate::options::Options;
+use crate::procfs::{is_alive, tree_alive};
+use crate::runner::{run_command, CommandRunner};
+use crate::service::watchdog;
+use crate::session::SessionType;
+use crate::state::{lock, FullscreenState, State, TrackAudioState};
+
+// What a window has to have to count as the app's, a title substring (empty
+// matches anything), the exact WM_CLASS with --match-class, a title regex
+// with --match-regex or the very id with --window-id.
+#[derive(Clone)]
+pub enum WindowMatch {
+    Title(String),
+    Class(String),
+    Regex(Regex),
+    Id(String)
+}
+
+// What --match checks to tell the tracked window is still there: its PID,
+// its name (the title, class or regex in `matching`) or both.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, ValueEnum)]
+pub enum MatchBy {
+    Pid,
+    Name,
+    Both
+}
+
+// The window we ended up tracking, it only counts as open for as long as it
+// keeps what `by` says to look at. Browsers open several top-level windows
+// under one PID, `others` are the rest of them as of the last look.
+#[derive(Clone)]
+pub struct TrackedWindow {
+    pub id: String,
+    pub pid: u32,
+    pub matching: WindowMatch,
+    pub by: MatchBy,
+    pub others: Vec<String>
+}
+
+impl TrackedWindow {
+    // With --match-regex audio streams are matched on their names too.
+    pub(crate) fn stream_regex(&self) -> Option<&Regex> {
+        match &self.matching {
+            WindowMatch::Regex(regex) => Some(regex),
+            _ => None
+        }
+    }
+}
+
+// The window most tests track, mpv playing video.mkv.
+#[cfg(test)]
+pub(crate) fn mpv_window() -> TrackedWindow {
+    TrackedWindow { id: "0x03a00006".to_owned(), pid: 9876, matching: WindowMatch::Title("mpv".to_owned()), by: MatchBy::Both, others: Vec::new() }
+}
+
+// The app's output goes nowhere unless there's a log to append it to. With
+// `shell` the app and its arguments are one command line for `sh -c`, so
+// quoting, pipes and variables work like they would in a terminal.
+pub(crate) fn launch_app(app_name: &str, args: &[String], shell: bool, display: Option<&str>, env: &[(String, String)], cwd: Option<&Path>, log: Option<&Path>) -> Result<Child, AttentionError> {
+    let (stdout, stderr) = match log {
+        Some(path) => {
+            let open_error = |source| AttentionError::AppLog { path: path.to_owned(), source };
+            let file = OpenOptions::new().create(true).append(true).open(path).map_err(open_error)?;
+            (Stdio::from(file.try_clone().map_err(open_error)?), Stdio::from(file))
+        }
+        None => (Stdio::null(), Stdio::null())
+    };
+    let mut command = if shell {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(format!("{} {}", app_name, args.join(" ")).trim_end());
+        command
+    } else {
+        let mut command = Command::new(app_name);
+        command.args(args);
+        command
+    };
+    if let Some(display) = display {
+        command.env("DISPLAY", display);
+    }
+    // The rest of our environment is passed on as is, --env comes last so
+    // it wins over --display too.
+    command.envs(env.iter().map(|(key, value)| (key, value)));
+    if let Some(cwd) = cwd {
+        command.current_dir(cwd);
+    }
+    command
+    .stdout(stdout)
+    .stderr(stderr)
+    .spawn()
+    .map_err(|source| AttentionError::CommandSpawn { cmd: app_name.to_owned(), source })
+}
+
+// One line of `wmctrl -lp`: window id, desktop, PID, host and the title,
+// which is the only column that can contain spaces. Desktop and host aren't
+// of any use to us so they're skipped.
+#[derive(Debug)]
+pub struct WindowInfo {
+    pub id: String,
+    pub pid: u32,
+    pub title: String,
+    // Sway hands it over with the tree, on X11 it takes an xprop per window.
+    pub class: Option<String>
+}
+
+impl WindowInfo {
+    // Asked for once the window is resolved, matching only asks when it has
+    // to.
+    fn with_class(mut self, runner: &dyn CommandRunner, session: SessionType) -> Self {
+        if self.class.is_none() {
+            match window_class(runner, session, &self.id) {
+                Ok(class) => self.class = class,
+                Err(err) => debug!("{}", err)
+            }
+        }
+        self
+    }
+
+    fn title_contains(&self, title_match: &str) -> bool {
+        self.title.to_lowercase().contains(&title_match.to_lowercase())
+    }
+
+    fn class(&self, runner: &dyn CommandRunner, session: SessionType) -> Result<Option<String>, AttentionError> {
+        match &self.class {
+            Some(class) => Ok(Some(class.clone())),
+            None => window_class(runner, session, &self.id)
+        }
+    }
+
+    fn matches(&self, runner: &dyn CommandRunner, session: SessionType, matching: &WindowMatch) -> Result<bool, AttentionError> {
+        match matching {
+            WindowMatch::Title(title_match) => Ok(self.title_contains(title_match)),
+            WindowMatch::Class(class) => Ok(self.class(runner, session)?.as_ref() == Some(class)),
+            WindowMatch::Regex(regex) => Ok(regex.is_match(&self.title)),
+            WindowMatch::Id(id) => Ok(window_id_number(id).is_some() && window_id_number(id) == window_id_number(&self.id))
+        }
+    }
+}
+
+// wmctrl pads ids with zeros where xprop and xwininfo don't, so they're
+// compared as numbers. Sway's con_id is decimal.
+pub(crate) fn window_id_number(id: &str) -> Option<u64> {
+    match id.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => id.parse().ok()
+    }
+}
+
+// Ids that aren't numbers at all can still be the same string.
+fn same_window_id(a: &str, b: &str) -> bool {
+    match (window_id_number(a), window_id_number(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b
+    }
+}
+
+pub(crate) fn parse_wmctrl_line(line: &str) -> Option<WindowInfo> {
+    let mut rest = line.trim_start();
+    let mut columns = Vec::with_capacity(4);
+    for _ in 0..4 {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if end == 0 {
+            return None;
+        }
+        columns.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+
+    Some(WindowInfo {
+        id: columns[0].to_owned(),
+        pid: columns[2].parse().ok()?,
+        title: rest.trim_end().to_owned(),
+        class: None
+    })
+}
+
+pub(crate) fn sway_tree(runner: &dyn CommandRunner) -> Result<Value, AttentionError> {
+    let output = run_command(runner, "swaymsg", &["-t", "get_tree", "-r"])?;
+
+    serde_json::from_slice(&output.stdout)
+    .map_err(|err| AttentionError::UnexpectedOutput { cmd: "swaymsg".to_owned(), reason: err.to_string() })
+}
+
+pub(crate) fn sway_children(node: &Value) -> impl Iterator<Item = &Value> {
+    ["nodes", "floating_nodes"]
+    .into_iter()
+    .filter_map(|key| node.get(key).and_then(Value::as_array))
+    .flatten()
+}
+
+// Only leaves that belong to a client carry a `pid`, everything above them is
+// outputs, workspaces and split containers.
+pub(crate) fn collect_sway_windows(node: &Value, windows: &mut Vec<WindowInfo>) {
+    if let (Some(id), Some(pid)) = (node.get("id").and_then(Value::as_u64), node.get("pid").and_then(Value::as_u64)) {
+        windows.push(WindowInfo {
+            id: id.to_string(),
+            pid: pid as u32,
+            title: node.get("name").and_then(Value::as_str).unwrap_or_default().to_owned(),
+            class: sway_class(node)
+        });
+    }
+    for child in sway_children(node) {
+        collect_sway_windows(child, windows);
+    }
+}
+
+fn sway_class(node: &Value) -> Option<String> {
+    node.get("app_id").and_then(Value::as_str)
+    .or_else(|| node["window_properties"]["class"].as_str())
+    .map(str::to_owned)
+}
+
+// Hyprland lists its windows flat, each client keyed by its address.
+pub(crate) fn hyprland_clients(runner: &dyn CommandRunner) -> Result<Vec<Value>, AttentionError> {
+    let output = run_command(runner, "hyprctl", &["clients", "-j"])?;
+
+    serde_json::from_slice(&output.stdout)
+    .map_err(|err| AttentionError::UnexpectedOutput { cmd: "hyprctl".to_owned(), reason: err.to_string() })
+}
+
+pub(crate) fn find_hyprland_client<'a>(clients: &'a [Value], address: &str) -> Option<&'a Value> {
+    clients.iter().find(|client| client["address"] == address)
+}
+
+fn hyprland_window(client: &Value) -> Option<WindowInfo> {
+    if client["mapped"] == false {
+        return None;
+    }
+    Some(WindowInfo {
+        id: client["address"].as_str()?.to_owned(),
+        pid: client["pid"].as_u64().and_then(|pid| u32::try_from(pid).ok())?,
+        title: client["title"].as_str().unwrap_or_default().to_owned(),
+        class: client["class"].as_str().map(str::to_owned)
+    })
+}
+
+// Older releases say `"fullscreen": true`, newer ones give the mode as a
+// number where 0 is not fullscreen.
+fn hyprland_fullscreen(client: &Value) -> bool {
+    match &client["fullscreen"] {
+        Value::Bool(fullscreen) => *fullscreen,
+        Value::Number(mode) => mode.as_u64().is_some_and(|mode| mode != 0),
+        _ => false
+    }
+}
+
+pub(crate) fn find_sway_node<'a>(node: &'a Value, id: &str) -> Option<&'a Value> {
+    if node.get("id").and_then(Value::as_u64).is_some_and(|node_id| node_id.to_string() == id) {
+        return Some(node);
+    }
+    sway_children(node).find_map(|child| find_sway_node(child, id))
+}
+
+pub(crate) fn list_windows(runner: &dyn CommandRunner, session: SessionType) -> Result<Vec<WindowInfo>, AttentionError> {
+    match session {
+        SessionType::X11 => {
+            let output = run_command(runner, "wmctrl", &["-lp"])?;
+
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            Ok(stdout.lines().filter_map(parse_wmctrl_line).collect())
+        }
+        SessionType::Sway => {
+            let mut windows = Vec::new();
+            collect_sway_windows(&sway_tree(runner)?, &mut windows);
+            Ok(windows)
+        }
+        SessionType::Hyprland => Ok(hyprland_clients(runner)?.iter().filter_map(hyprland_window).collect())
+    }
+}
+
+// The first of `windows` matching, and of `pid` when that's given, taken
+// out of the list.
+fn take_window(runner: &dyn CommandRunner, session: SessionType, windows: &mut Vec<WindowInfo>, matching: &WindowMatch, pid: Option<u32>) -> Result<Option<WindowInfo>, AttentionError> {
+    for at in 0..windows.len() {
+        if pid.is_none_or(|pid| windows[at].pid == pid) && windows[at].matches(runner, session, matching)? {
+            return Ok(Some(windows.swap_remove(at)));
+        }
+    }
+    Ok(None)
+}
+
+fn find_window_info(runner: &dyn CommandRunner, session: SessionType, matching: &WindowMatch, pid: u32) -> Result<Option<WindowInfo>, AttentionError> {
+    take_window(runner, session, &mut list_windows(runner, session)?, matching, Some(pid))
+}
+
+pub(crate) fn find_window(runner: &dyn CommandRunner, session: SessionType, matching: &WindowMatch, pid: u32) -> Result<Option<String>, AttentionError> {
+    Ok(find_window_info(runner, session, matching, pid)?.map(|window| window.id))
+}
+
+// The tracked window's id as it is now, None once it's no longer there.
+pub(crate) fn find_tracked_window(runner: &dyn CommandRunner, session: SessionType, window: &TrackedWindow) -> Result<Option<String>, AttentionError> {
+    match window.by {
+        MatchBy::Pid => find_window(runner, session, &WindowMatch::Title(String::new()), window.pid),
+        MatchBy::Name => Ok(find_matching_window(runner, session, &window.matching)?.map(|(id, _)| id)),
+        MatchBy::Both => find_window(runner, session, &window.matching, window.pid)
+    }
+}
+
+// Every window that counts as the app's, the way --match says.
+pub(crate) fn find_tracked_windows(runner: &dyn CommandRunner, session: SessionType, window: &TrackedWindow) -> Result<Vec<String>, AttentionError> {
+    let mut ids = Vec::new();
+    for entry in list_windows(runner, session)? {
+        let ours = match window.by {
+            MatchBy::Pid => entry.pid == window.pid,
+            MatchBy::Name => entry.matches(runner, session, &window.matching)?,
+            MatchBy::Both => entry.pid == window.pid && entry.matches(runner, session, &window.matching)?
+        };
+        if ours {
+            ids.push(entry.id);
+        }
+    }
+    Ok(ids)
+}
+
+// Windows come and go, the app's other ones are looked for again this often.
+const OTHERS_EVERY: time::Duration = time::Duration::from_secs(5);
+
+pub(crate) fn refresh_other_windows(runner: &dyn CommandRunner, window: &mut TrackedWindow, state: &mut State) -> Result<(), AttentionError> {
+    if state.windows_listed_at.is_some_and(|listed_at| listed_at.elapsed() < OTHERS_EVERY) {
+        return Ok(());
+    }
+    state.windows_listed_at = Some(Instant::now());
+    window.others = find_tracked_windows(runner, state.session, window)?
+    .into_iter()
+    .filter(|id| !same_window_id(id, &window.id))
+    .collect();
+    state.window = Some(window.clone());
+    Ok(())
+}
+
+fn find_matching_window_info(runner: &dyn CommandRunner, session: SessionType, matching: &WindowMatch) -> Result<Option<WindowInfo>, AttentionError> {
+    take_window(runner, session, &mut list_windows(runner, session)?, matching, None)
+}
+
+pub(crate) fn find_matching_window(runner: &dyn CommandRunner, session: SessionType, matching: &WindowMatch) -> Result<Option<(String, u32)>, AttentionError> {
+    Ok(find_matching_window_info(runner, session, matching)?.map(|window| (window.id, window.pid)))
+}
+
+// Whether the app we launched is gone, or there never was one.
+fn child_exited(state: &mut State) -> bool {
+    state.child.as_mut().is_none_or(|child| matches!(child.try_wait(), Ok(Some(_))))
+}
+
+// How the launched app ended, if it did and not cleanly, for --restart-app.
+pub(crate) fn app_crashed(state: &mut State) -> Option<ExitStatus> {
+    let status = state.child.as_mut()?.try_wait().ok()??;
+    (!status.success()).then_some(status)
+}
+
+// How long the launched app gets to quit on SIGTERM before it's killed.
+const KILL_AFTER: time::Duration = time::Duration::from_secs(5);
+
+// With --kill-on-exit the app we launched goes down with us, politely first.
+pub(crate) fn stop_app(runner: &dyn CommandRunner, state: &mut State) {
+    if !state.kill_on_exit || child_exited(state) {
+        return;
+    }
+    let Some(mut child) = state.child.take() else {
+        return;
+    };
+
+    if let Err(err) = run_command(runner, "kill", &["-TERM", &child.id().to_string()]) {
+        warn!("{}", err);
+    }
+    let started = Instant::now();
+    while started.elapsed() < KILL_AFTER {
+        if matches!(child.try_wait(), Ok(Some(_))) {
+            return;
+        }
+        sleep(time::Duration::from_millis(100));
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+// How long the launched PID gets to show a window of its own before we settle
+// for any window with the app's name in it.
+pub(crate) const PID_FALLBACK_AFTER: time::Duration = time::Duration::from_secs(2);
+
+// --app-ready-cmd runs through the shell, anything but a zero exit means
+// not yet.
+fn app_ready(runner: &dyn CommandRunner, cmd: &str) -> bool {
+    match run_command(runner, "sh", &["-c", cmd]) {
+        Ok(_) => true,
+        Err(err) => {
+            debug!("Not ready yet, {}..", err);
+            false
+        }
+    }
+}
+
+// Browsers and flatpaks hand the window over to some other process, sometimes
+// with the launched one exiting right away, so after a short while the title
+// alone is enough and whichever PID owns that window is adopted. `--match pid`
+// takes any window of the launched PID, `--match name` doesn't wait to settle
+// for the title. With --app-ready-cmd no window is looked at until that says
+// the app is ready, and the fallback counts from then. The launched app lives
+// in `state.child` so a signal can still stop it meanwhile.
+pub(crate) fn wait_for_window_to_show_up(runner: &dyn CommandRunner, session: SessionType, app_name: &str, matching: &WindowMatch, pid: u32, state: &Mutex<State>, options: &Options) -> Result<WindowInfo, AttentionError> {
+    let (interval, timeout) = (options.startup_interval, options.window_timeout);
+    let started = Instant::now();
+    let mut ready_at = options.app_ready_cmd.is_none().then_some(started);
+    let mut exited_at: Option<Instant> = None;
+    let mut listed_any = false;
+    let mut warned = false;
+    let own = match options.match_by {
+        MatchBy::Pid => WindowMatch::Title(String::new()),
+        _ => matching.clone()
+    };
+    loop {
+        watchdog();
+        if ready_at.is_none() && options.app_ready_cmd.as_deref().is_some_and(|cmd| app_ready(runner, cmd)) {
+            ready_at = Some(Instant::now());
+        }
+
+        if exited_at.is_none() && child_exited(&mut lock(state)) {
+            exited_at = Some(Instant::now());
+        }
+
+        if let Some(ready_at) = ready_at {
+            let mut windows = list_windows(runner, session)?;
+            // Nothing at all listed, rather than nothing of the app's, is a
+            // window manager without the EWMH client list or one not up yet.
+            listed_any |= !windows.is_empty();
+            if !listed_any && !warned && session == SessionType::X11 && ready_at.elapsed() >= PID_FALLBACK_AFTER {
+                warn!("wmctrl lists no windows at all, the window manager may not support the EWMH hints attention needs..");
+                warned = true;
+            }
+
+            if let Some(window) = take_window(runner, session, &mut windows, &own, Some(pid))? {
+                return Ok(window.with_class(runner, session));
+            }
+            if (exited_at.is_some() || ready_at.elapsed() >= PID_FALLBACK_AFTER || options.match_by == MatchBy::Name)
+                && let Some(window) = take_window(runner, session, &mut windows, matching, None)? {
+                return Ok(window.with_class(runner, session));
+            }
+        }
+
+        if exited_at.is_some_and(|exited_at| exited_at.elapsed() >= PID_FALLBACK_AFTER) {
+            return Err(AttentionError::WindowNotFound { app_name: app_name.to_owned() });
+        }
+
+        if started.elapsed() >= timeout {
+            if ready_at.is_some() && !listed_any && session == SessionType::X11 {
+                return Err(AttentionError::NoWindowsListed { timeout });
+            }
+            return Err(AttentionError::WindowTimeout { app_name: app_name.to_owned(), timeout });
+        }
+
+        sleep(interval);
+    }
+}
+
+pub(crate) fn process_name(pid: u32) -> Option<String> {
+    fs::read_to_string(format!("/proc/{}/comm", pid))
+    .ok()
+    .map(|comm| comm.trim().to_owned())
+}
+
+// Looks at what `wmctrl` reports, so it works the same whether we spawned the
+// PID or attached to it. If the PID dies and gets reused by some other window
+// matching the title we'd keep going, which is unlikely enough to ignore.
+pub(crate) fn is_window_closed(runner: &dyn CommandRunner, app_name: &str, window: &TrackedWindow, state: &mut State) -> Result<bool, AttentionError> {
+    if find_tracked_window(runner, state.session, window)?.is_some() {
+        state.window_gone_since = None;
+        return Ok(false);
+    }
+    if !window_gone(window.pid, state) {
+        return Ok(false);
+    }
+    window_closed(runner, app_name, state)?;
+    Ok(true)
+}
+
+// What xprop says about an id whose window has been destroyed.
+pub(crate) fn window_stale(err: &AttentionError) -> bool {
+    match err {
+        AttentionError::CommandFailed { stderr, .. } => stderr.contains("BadWindow") || stderr.to_lowercase().contains("no such window"),
+        _ => false
+    }
+}
+
+// Apps can destroy their window and map a new one, going borderless
+// fullscreen or after losing their GPU context, leaving the id we had
+// pointing at nothing. The window is looked up again the way --match says
+// until one with a new id turns up, `err` stands once that's taken longer
+// than --window-gone-grace.
+pub(crate) fn refresh_window(runner: &dyn CommandRunner, window: &TrackedWindow, err: AttentionError, state: &mut State) -> Result<Option<TrackedWindow>, AttentionError> {
+    let since = *state.window_stale_since.get_or_insert_with(Instant::now);
+    match find_tracked_window(runner, state.session, window)? {
+        Some(id) if id != window.id => {
+            debug!("Window {} is gone, {} took its place..", window.id, id);
+            state.window_stale_since = None;
+            let window = TrackedWindow { id, ..window.clone() };
+            state.window = Some(window.clone());
+            Ok(Some(window))
+        }
+        _ if since.elapsed() >= state.window_gone_grace => Err(err),
+        _ => Ok(None)
+    }
+}
+
+// A window can go missing while its app lives on, a splash screen handing
+// over to the real window or a trip to the tray. It only counts as closed
+// once the process tree is gone too, or it's stayed missing for
+// --window-gone-grace.
+pub(crate) fn window_gone(pid: u32, state: &mut State) -> bool {
+    let since = *state.window_gone_since.get_or_insert_with(Instant::now);
+    if tree_alive(pid) && since.elapsed() < state.window_gone_grace {
+        debug!("The window is gone but PID {} is still running, waiting for it..", pid);
+        return false;
+    }
+    true
+}
+
+pub(crate) fn window_closed(runner: &dyn CommandRunner, app_name: &str, state: &mut State) -> Result<(), AttentionError> {
+    report(state.json, Event::WindowClosed { app: app_name });
+    // With --apps the others may still want the screen on.
+    if state.reasons_elsewhere().next().is_none() {
+        turn_on_screen_blanking(runner, state)?;
+    }
+    state.inhibit_reasons.clear();
+    state.last_fullscreen_state = FullscreenState::NotFullscreen;
+    state.fullscreen_pending = 0;
+    state.last_track_audio_state = TrackAudioState::Off;
+    state.mpris_playing = false;
+    state.silence_since = None;
+    Ok(())
+}
+
+// Our own child is asked directly, which reaps it, anything else is alive
+// for as long as /proc has it and it isn't a zombie.
+fn process_alive(pid: u32, state: &mut State) -> bool {
+    if let Some(child) = state.child.as_mut().filter(|child| child.id() == pid) {
+        return !matches!(child.try_wait(), Ok(Some(_)));
+    }
+    is_alive(pid)
+}
+
+// With --persist a closed window isn't the end, apps that close to the tray
+// get their next window adopted. None once the process itself is gone.
+pub(crate) fn wait_for_window_to_reopen(runner: &dyn CommandRunner, session: SessionType, window: &TrackedWindow, interval: time::Duration, state: &Mutex<State>) -> Result<Option<TrackedWindow>, AttentionError> {
+    loop {
+        watchdog();
+        if let Some(id) = find_tracked_window(runner, session, window)? {
+            return Ok(Some(TrackedWindow { id, ..window.clone() }));
+        }
+        if !matches!(&window.matching, WindowMatch::Title(title) if title.is_empty())
+            && let Some((id, pid)) = find_matching_window(runner, session, &window.matching)? {
+            return Ok(Some(TrackedWindow { id, pid, ..window.clone() }));
+        }
+        if !process_alive(window.pid, &mut lock(state)) {
+            return Ok(None);
+        }
+        sleep(interval);
+    }
+}
+
+// `WM_CLASS(STRING) = "instance", "Class"`, the class is the second one.
+pub(crate) fn parse_wm_class(stdout: &str) -> Option<(String, String)> {
+    let (property, values) = stdout.trim().split_once(" = ")?;
+    if property != "WM_CLASS(STRING)" {
+        return None;
+    }
+    let (instance, class) = values.split_once(", ")?;
+    Some((instance.trim_matches('"').to_owned(), class.trim_matches('"').to_owned()))
+}
+
+pub(crate) fn window_class(runner: &dyn CommandRunner, session: SessionType, window_id: &str) -> Result<Option<String>, AttentionError> {
+    match session {
+        SessionType::X11 => {
+            let output = run_command(runner, "xprop", &["-id", window_id, "WM_CLASS"])?;
+            Ok(parse_wm_class(&String::from_utf8_lossy(&output.stdout)).map(|(_, class)| class))
+        }
+        SessionType::Sway => {
+            let tree = sway_tree(runner)?;
+            Ok(find_sway_node(&tree, window_id).and_then(sway_class))
+        }
+        SessionType::Hyprland => Ok(find_hyprland_client(&hyprland_clients(runner)?, window_id)
+        .and_then(|client| client["class"].as_str())
+        .map(str::to_owned))
+    }
+}
+
+// Backs `attention list`, shows what the app name gets matched against.
+pub(crate) fn print_windows(runner: &dyn CommandRunner, session: SessionType, with_class: bool) -> Result<(), AttentionError> {
+    let windows = list_windows(runner, session)?;
+    let mut rows = vec![("ID".to_owned(), "PID".to_owned(), "CLASS".to_owned(), "TITLE".to_owned())];
+    for window in windows {
+        let class = if with_class {
+            window.class(runner, session)?.unwrap_or_default()
+        } else {
+            String::new()
+        };
+        rows.push((window.id, window.pid.to_string(), class, window.title));
+    }
+
+    let id_width = rows.iter().map(|row| row.0.len()).max().unwrap_or(0);
+    let pid_width = rows.iter().map(|row| row.1.len()).max().unwrap_or(0);
+    let class_width = rows.iter().map(|row| row.2.chars().count()).max().unwrap_or(0);
+    for (id, pid, class, title) in rows {
+        if with_class {
+            println!("{:id_width$}  {:>pid_width$}  {:class_width$}  {}", id, pid, class, title);
+        } else {
+            println!("{:id_width$}  {:>pid_width$}  {}", id, pid, title);
+        }
+    }
+    Ok(())
+}
+
+// Backs `attention debug`, the raw wmctrl line for the window, or what sway
+// says about it.
+pub(crate) fn window_lines(runner: &dyn CommandRunner, session: SessionType, window_id: &str) -> Result<Vec<String>, AttentionError> {
+    match session {
+        SessionType::X11 => {
+            let output = run_command(runner, "wmctrl", &["-lp"])?;
+
+            Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|line| parse_wmctrl_line(line).is_some_and(|window| window.id == window_id))
+            .map(|line| format!("wmctrl: {}", line))
+            .collect())
+        }
+        SessionType::Sway => Ok(list_windows(runner, session)?
+        .into_iter()
+        .filter(|window| window.id == window_id)
+        .map(|window| format!("swaymsg: {} {} {} {}", window.id, window.pid, window.class.unwrap_or_default(), window.title))
+        .collect()),
+        SessionType::Hyprland => Ok(find_hyprland_client(&hyprland_clients(runner)?, window_id)
+        .map(|client| format!("hyprctl: {}", client))
+        .into_iter()
+        .collect())
+    }
+}
+
+// `_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00006`
+pub(crate) fn parse_active_window(stdout: &str) -> Option<String> {
+    let (property, id) = stdout.trim().split_once(" # ")?;
+    property.starts_with("_NET_ACTIVE_WINDOW").then(|| id.trim().to_owned())
+}
+
+pub(crate) fn active_window_id(runner: &dyn CommandRunner) -> Result<String, AttentionError> {
+    let output = run_command(runner, "xprop", &["-root", "_NET_ACTIVE_WINDOW"])?;
+
+    parse_active_window(&String::from_utf8_lossy(&output.stdout))
+    .ok_or_else(|| AttentionError::UnexpectedOutput { cmd: "xprop".to_owned(), reason: "no _NET_ACTIVE_WINDOW".to_owned() })
+}
+
+pub(crate) fn is_window_focused(runner: &dyn CommandRunner, session: SessionType, window_id: &str) -> Result<bool, AttentionError> {
+    match session {
+        SessionType::X11 => Ok(same_window_id(&active_window_id(runner)?, window_id)),
+        SessionType::Sway => {
+            let tree = sway_tree(runner)?;
+            Ok(find_sway_node(&tree, window_id)
+            .and_then(|node| node.get("focused"))
+            .and_then(Value::as_bool)
+            .unwrap_or(false))
+        }
+        // The focus history starts at the focused window.
+        SessionType::Hyprland => Ok(find_hyprland_client(&hyprland_clients(runner)?, window_id)
+        .is_some_and(|client| client["focusHistoryID"] == 0))
+    }
+}
+
+// The `_NET_WM_STATE(ATOM)` line lists every state the window is in, the
+// same whether it's polled for or comes from `xprop -spy`.
+pub(crate) fn state_is_fullscreen(line: &str) -> bool {
+    line.split_once('=')
+    .filter(|(property, _)| property.trim() == "_NET_WM_STATE(ATOM)")
+    .is_some_and(|(_, atoms)| atoms.split(',').any(|atom| atom.trim() == "_NET_WM_STATE_FULLSCREEN"))
+}
+
+pub(crate) fn is_window_fullscreen(runner: &dyn CommandRunner, session: SessionType, window_id: &str) -> Result<bool, AttentionError> {
+    match session {
+        SessionType::X11 => {
+            let output = run_command(runner, "xprop", &["-id", window_id])?;
+            Ok(String::from_utf8_lossy(&output.stdout).lines().any(state_is_fullscreen))
+        }
+        SessionType::Sway => {
+            let tree = sway_tree(runner)?;
+            Ok(find_sway_node(&tree, window_id)
+            .and_then(|node| node.get("fullscreen_mode"))
+            .and_then(Value::as_u64)
+            .is_some_and(|mode| mode != 0))
+        }
+        SessionType::Hyprland => Ok(find_hyprland_client(&hyprland_clients(runner)?, window_id).is_some_and(hyprland_fullscreen))
+    }
+}
+
+// Any of the app's windows being fullscreen counts. The tracked id answers
+// for itself, another one that's gone since the last look just doesn't.
+pub(crate) fn is_app_fullscreen(runner: &dyn CommandRunner, session: SessionType, window: &TrackedWindow) -> Result<bool, AttentionError> {
+    if is_window_fullscreen(runner, session, &window.id)? {
+        return Ok(true);
+    }
+    for id in &window.others {
+        match is_window_fullscreen(runner, session, id) {
+            Ok(true) => return Ok(true),
+            Ok(false) => (),
+            Err(err) => debug!("{}", err)
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::runner::MockRunner;
+
+    const WMCTRL: &str = "\
+0x02200003  0 2451   desk Mozilla Firefox
+0x03a00006  0 9876   desk video.mkv - mpv
+";
+
+    const XPROP_FULLSCREEN: &str = "\
+_NET_WM_USER_TIME(CARDINAL) = 372610
+_NET_WM_STATE(ATOM) = _NET_WM_STATE_FULLSCREEN
+WM_NAME(STRING) = \"video.mkv - mpv\"
+";
+
+    fn window(pid: u32, title_match: &str) -> TrackedWindow {
+        TrackedWindow { pid, matching: WindowMatch::Title(title_match.to_owned()), ..mpv_window() }
+    }
+
+    #[test]
+    fn a_running_app_gets_the_grace_to_bring_its_window_back() {
+        let runner = MockRunner::new();
+        let mut state = State::new(SessionType::X11);
+        state.window_gone_grace = time::Duration::from_secs(60);
+        let ours = window(std::process::id(), "mpv");
+        assert!(!is_window_closed(&runner, "mpv", &ours, &mut state).unwrap());
+
+        state.window_gone_since = Instant::now().checked_sub(time::Duration::from_secs(61));
+        assert!(is_window_closed(&runner, "mpv", &ours, &mut state).unwrap());
+    }
+
+    #[test]
+    fn fullscreen_is_detected_from_xprop() {
+        let runner = MockRunner::new().with("xprop", XPROP_FULLSCREEN);
+        assert!(is_window_fullscreen(&runner, SessionType::X11, "0x03a00006").unwrap());
+        assert_eq!(runner.calls(), vec!["xprop -id 0x03a00006"]);
+    }
+
+    #[test]
+    fn window_without_the_state_is_not_fullscreen() {
+        let runner = MockRunner::new().with("xprop", "_NET_WM_STATE(ATOM) = _NET_WM_STATE_MAXIMIZED_VERT\n");
+        assert!(!is_window_fullscreen(&runner, SessionType::X11, "0x03a00006").unwrap());
+    }
+
+    #[test]
+    fn fullscreen_is_found_among_the_other_states() {
+        let runner = MockRunner::new().with("xprop", "_NET_WM_STATE(ATOM) = _NET_WM_STATE_ABOVE, _NET_WM_STATE_FULLSCREEN\n");
+        assert!(is_window_fullscreen(&runner, SessionType::X11, "0x03a00006").unwrap());
+    }
+
+    #[test]
+    fn state_lines_are_parsed() {
+        assert!(state_is_fullscreen("_NET_WM_STATE(ATOM) = _NET_WM_STATE_FULLSCREEN"));
+        assert!(state_is_fullscreen("_NET_WM_STATE(ATOM) = _NET_WM_STATE_FOCUSED, _NET_WM_STATE_FULLSCREEN"));
+        assert!(!state_is_fullscreen("_NET_WM_STATE(ATOM) = _NET_WM_STATE_MAXIMIZED_VERT"));
+        assert!(!state_is_fullscreen("_NET_WM_STATE(ATOM) = "));
+        assert!(!state_is_fullscreen("_NET_WM_STATE:  not found."));
+    }
+
+    #[test]
+    fn open_window_is_not_closed() {
+        let runner = MockRunner::new().with("wmctrl", WMCTRL);
+        let mut state = State::new(SessionType::X11);
+        assert!(!is_window_closed(&runner, "mpv", &window(9876, "mpv"), &mut state).unwrap());
+    }
+
+    #[test]
+    fn only_an_unclean_exit_counts_as_a_crash() {
+        let mut state = State::new(SessionType::X11);
+        assert!(app_crashed(&mut state).is_none());
+        for (script, crashed) in [("exit 3", true), ("exit 0", false)] {
+            let mut child = Command::new("sh").args(["-c", script]).spawn().unwrap();
+            child.wait().unwrap();
+            state.child = Some(child);
+            assert_eq!(app_crashed(&mut state).is_some(), crashed);
+        }
+    }
+
+    #[test]
+    fn missing_window_is_closed() {
+        let runner = MockRunner::new().with("wmctrl", WMCTRL);
+        let mut state = State::new(SessionType::X11);
+        assert!(is_window_closed(&runner, "vlc", &window(1111, "vlc"), &mut state).unwrap());
+    }
+
+    const WMCTRL_PREFIX_PIDS: &str = "\
+0x01000007 -1 1234   desk xfce4-panel
+0x02200003  0 12345  desk mpv - Mozilla Firefox
+0x02400011  1 123    desk Terminal - mpv --help
+0x03a00006  0 1234   desk
+";
+
+    #[test]
+    fn wmctrl_lines_are_split_into_columns() {
+        let window = parse_wmctrl_line("0x02200003  0 12345  desk mpv - Mozilla Firefox").unwrap();
+        assert_eq!(window.id, "0x02200003");
+        assert_eq!(window.pid, 12345);
+        assert_eq!(window.title, "mpv - Mozilla Firefox");
+        assert!(parse_wmctrl_line("").is_none());
+        assert!(parse_wmctrl_line("0x02200003  0 notapid desk title").is_none());
+    }
+
+    #[test]
+    fn pid_is_matched_exactly_not_as_a_prefix() {
+        let runner = MockRunner::new().with("wmctrl", WMCTRL_PREFIX_PIDS);
+        assert_eq!(find_window(&runner, SessionType::X11, &WindowMatch::Title("mpv".to_owned()), 12345).unwrap(), Some("0x02200003".to_owned()));
+        assert_eq!(find_window(&runner, SessionType::X11, &WindowMatch::Title("mpv".to_owned()), 1234).unwrap(), None);
+        assert_eq!(find_window(&runner, SessionType::X11, &WindowMatch::Title("mpv".to_owned()), 12).unwrap(), None);
+    }
+
+    #[test]
+    fn match_strategies_look_at_the_pid_the_name_or_both() {
+        let runner = MockRunner::new().with("wmctrl", WMCTRL_PREFIX_PIDS);
+        let tracked = |pid, title: &str, by| TrackedWindow { id: String::new(), pid, matching: WindowMatch::Title(title.to_owned()), by, others: Vec::new() };
+        let find = |window: &TrackedWindow| find_tracked_window(&runner, SessionType::X11, window).unwrap();
+
+        assert_eq!(find(&tracked(123, "vlc", MatchBy::Pid)), Some("0x02400011".to_owned()));
+        assert_eq!(find(&tracked(12, "mpv", MatchBy::Pid)), None);
+        assert_eq!(find(&tracked(1, "firefox", MatchBy::Name)), Some("0x02200003".to_owned()));
+        assert_eq!(find(&tracked(12345, "vlc", MatchBy::Name)), None);
+        assert_eq!(find(&tracked(123, "mpv", MatchBy::Both)), Some("0x02400011".to_owned()));
+        assert_eq!(find(&tracked(123, "firefox", MatchBy::Both)), None);
+    }
+
+    #[test]
+    fn a_window_id_matches_however_its_padded() {
+        let runner = MockRunner::new().with("wmctrl", WMCTRL);
+        let id = |id: &str| WindowMatch::Id(id.to_owned());
+        assert_eq!(find_matching_window(&runner, SessionType::X11, &id("0x3a00006")).unwrap(), Some(("0x03a00006".to_owned(), 9876)));
+        assert_eq!(find_window(&runner, SessionType::X11, &id("0x03a00006"), 2451).unwrap(), None);
+        assert_eq!(find_matching_window(&runner, SessionType::X11, &id("0x04c00002")).unwrap(), None);
+        assert!(same_window_id("0x3a00006", "0x03a00006"));
+        // Sway's con_ids are decimal, 10 isn't 0x10.
+        assert!(same_window_id("16", "16") && !same_window_id("10", "16"));
+    }
+
+    #[test]
+    fn app_name_is_matched_against_the_title_only() {
+        let runner = MockRunner::new().with("wmctrl", "0x03a00006  0 4242   mpv-host Some Video\n");
+        assert_eq!(find_window(&runner, SessionType::X11, &WindowMatch::Title("mpv".to_owned()), 4242).unwrap(), None);
+        assert_eq!(find_window(&runner, SessionType::X11, &WindowMatch::Title("some video".to_owned()), 4242).unwrap(), Some("0x03a00006".to_owned()));
+    }
+
+    #[test]
+    fn name_on_another_pids_window_does_not_keep_ours_open() {
+        let runner = MockRunner::new().with("wmctrl", WMCTRL_PREFIX_PIDS);
+        let mut state = State::new(SessionType::X11);
+        assert!(is_window_closed(&runner, "mpv", &window(1234, "mpv"), &mut state).unwrap());
+        assert!(!is_window_closed(&runner, "mpv", &window(123, "mpv"), &mut state).unwrap());
+    }
+
+    const SWAY_TREE: &str = r#"{
+        "id": 1, "type": "root", "name": "root", "nodes": [
+            {"id": 3, "type": "output", "name": "eDP-1", "nodes": [
+                {"id": 5, "type": "workspace", "name": "1", "nodes": [
+                    {"id": 8, "type": "con", "name": "Alacritty", "pid": 2001, "fullscreen_mode": 0, "nodes": []},
+                    {"id": 9, "type": "con", "name": "video.mkv - mpv", "pid": 2002, "fullscreen_mode": 1, "nodes": []}
+                ], "floating_nodes": [
+                    {"id": 12, "type": "floating_con", "name": "Picture-in-Picture", "pid": 2003, "fullscreen_mode": 0, "nodes": []}
+                ]}
+            ]}
+        ]
+    }"#;
+
+    #[test]
+    fn any_of_the_apps_windows_being_fullscreen_counts() {
+        let runner = MockRunner::new().with("wmctrl", "0x03a00006  0 9876   desk video.mkv - mpv\n0x03a0000b  0 9876   desk clip.mkv - mpv\n0x02200003  0 2451   desk Mozilla Firefox\n");
+        let mut state = State::new(SessionType::X11);
+        let mut tracked = window(9876, "mpv");
+        refresh_other_windows(&runner, &mut tracked, &mut state).unwrap();
+        assert_eq!(tracked.others, ["0x03a0000b"]);
+
+        let runner = MockRunner::new().with("swaymsg", SWAY_TREE);
+        let tracked = |others: &[&str]| TrackedWindow { id: "8".to_owned(), pid: 2001, matching: WindowMatch::Title(String::new()), by: MatchBy::Pid, others: others.iter().map(|id| id.to_string()).collect() };
+        assert!(is_app_fullscreen(&runner, SessionType::Sway, &tracked(&["9"])).unwrap());
+        assert!(!is_app_fullscreen(&runner, SessionType::Sway, &tracked(&["12"])).unwrap());
+    }
+
+    #[test]
+    fn windows_are_only_looked_for_once_the_app_says_it_is_ready() {
+        let options = Options::parse_from(["attention", "--app-ready-cmd", "ss -ltn | grep -q :8080", "--window-timeout", "0", "mpv"]);
+        let state = Mutex::new(State::new(SessionType::X11));
+        let matching = WindowMatch::Title("mpv".to_owned());
+
+        let runner = MockRunner::new().failing("sh", "").with("wmctrl", WMCTRL);
+        let err = wait_for_window_to_show_up(&runner, SessionType::X11, "mpv", &matching, 9876, &state, &options).unwrap_err();
+        assert!(matches!(err, AttentionError::WindowTimeout { .. }));
+        assert_eq!(runner.calls(), ["sh -c ss -ltn | grep -q :8080"]);
+
+        let runner = MockRunner::new().with("sh", "").with("wmctrl", WMCTRL);
+        let runner = runner.with("xprop", "WM_CLASS(STRING) = \"mpv\", \"mpv\"\n");
+        let found = wait_for_window_to_show_up(&runner, SessionType::X11, "mpv", &matching, 9876, &state, &options).unwrap();
+        assert_eq!((found.id.as_str(), found.pid, found.title.as_str(), found.class.as_deref()), ("0x03a00006", 9876, "video.mkv - mpv", Some("mpv")));
+    }
+
+    #[test]
+    fn no_windows_at_all_is_its_own_error() {
+        let options = Options::parse_from(["attention", "--window-timeout", "0", "mpv"]);
+        let state = Mutex::new(State::new(SessionType::X11));
+        let matching = WindowMatch::Title("mpv".to_owned());
+
+        let runner = MockRunner::new().with("wmctrl", "");
+        let err = wait_for_window_to_show_up(&runner, SessionType::X11, "mpv", &matching, 9876, &state, &options).unwrap_err();
+        assert!(matches!(err, AttentionError::NoWindowsListed { .. }));
+
+        let runner = MockRunner::new().with("wmctrl", "0x01e00003  0 1234   host xterm\n");
+        let err = wait_for_window_to_show_up(&runner, SessionType::X11, "mpv", &matching, 9876, &state, &options).unwrap_err();
+        assert!(matches!(err, AttentionError::WindowTimeout { .. }));
+    }
+
+    #[test]
+    fn sway_windows_are_found_in_the_tree() {
+        let runner = MockRunner::new().with("swaymsg", SWAY_TREE);
+        let windows = list_windows(&runner, SessionType::Sway).unwrap();
+        let ids: Vec<&str> = windows.iter().map(|window| window.id.as_str()).collect();
+        assert_eq!(ids, vec!["8", "9", "12"]);
+        assert_eq!(find_window(&runner, SessionType::Sway, &WindowMatch::Title("mpv".to_owned()), 2002).unwrap(), Some("9".to_owned()));
+    }
+
+    #[test]
+    fn sway_fullscreen_is_read_from_the_tree() {
+        let runner = MockRunner::new().with("swaymsg", SWAY_TREE);
+        assert!(is_window_fullscreen(&runner, SessionType::Sway, "9").unwrap());
+        assert!(!is_window_fullscreen(&runner, SessionType::Sway, "8").unwrap());
+        assert!(!is_window_fullscreen(&runner, SessionType::Sway, "404").unwrap());
+    }
+
+    const HYPRCTL_CLIENTS: &str = r#"[
+        {"address": "0x55d1c2a0", "mapped": true, "class": "Alacritty", "title": "~", "pid": 2001, "fullscreen": 0, "focusHistoryID": 1},
+        {"address": "0x55d1c3f0", "mapped": true, "class": "mpv", "title": "video.mkv - mpv", "pid": 2002, "fullscreen": 2, "focusHistoryID": 0},
+        {"address": "0x55d1c4b0", "mapped": true, "class": "firefox", "title": "Mozilla Firefox", "pid": 2003, "fullscreen": false, "focusHistoryID": 2},
+        {"address": "0x55d1c5c0", "mapped": false, "class": "mpv", "title": "", "pid": 2004, "fullscreen": true, "focusHistoryID": -1}
+    ]"#;
+
+    #[test]
+    fn hyprland_clients_are_read_from_hyprctl() {
+        let runner = MockRunner::new().with("hyprctl", HYPRCTL_CLIENTS);
+        let windows = list_windows(&runner, SessionType::Hyprland).unwrap();
+        let ids: Vec<&str> = windows.iter().map(|window| window.id.as_str()).collect();
+        assert_eq!(ids, vec!["0x55d1c2a0", "0x55d1c3f0", "0x55d1c4b0"]);
+        assert_eq!(find_window(&runner, SessionType::Hyprland, &WindowMatch::Class("mpv".to_owned()), 2002).unwrap(), Some("0x55d1c3f0".to_owned()));
+        assert!(is_window_fullscreen(&runner, SessionType::Hyprland, "0x55d1c3f0").unwrap());
+        assert!(!is_window_fullscreen(&runner, SessionType::Hyprland, "0x55d1c4b0").unwrap());
+        assert!(is_window_focused(&runner, SessionType::Hyprland, "0x55d1c3f0").unwrap());
+        assert!(!is_window_focused(&runner, SessionType::Hyprland, "0x55d1c2a0").unwrap());
+    }
+
+    #[test]
+    fn failing_command_is_an_error() {
+        let runner = MockRunner::new().failing("wmctrl", "Cannot open display.");
+        let mut state = State::new(SessionType::X11);
+        let err = is_window_closed(&runner, "mpv", &window(9876, "mpv"), &mut state).err().unwrap();
+        assert_eq!(err.to_string(), "Command wmctrl returned error: Cannot open display.");
+    }
+
+    #[test]
+    fn a_reopened_window_is_adopted_by_title() {
+        let runner = MockRunner::new().with("wmctrl", "0x04400002  0 5555   desk Playlist - mpv\n");
+        let state = Mutex::new(State::new(SessionType::X11));
+        let reopened = wait_for_window_to_reopen(&runner, SessionType::X11, &window(9876, "mpv"), time::Duration::ZERO, &state).unwrap().unwrap();
+        assert_eq!((reopened.id.as_str(), reopened.pid), ("0x04400002", 5555));
+    }
+
+    #[test]
+    fn wm_class_is_parsed_from_xprop() {
+        assert_eq!(parse_wm_class("WM_CLASS(STRING) = \"gl\", \"mpv\"\n"), Some(("gl".to_owned(), "mpv".to_owned())));
+        assert_eq!(parse_wm_class("WM_CLASS:  not found.\n"), None);
+    }
+
+    #[test]
+    fn class_matching_is_exact() {
+        let runner = MockRunner::new().with("wmctrl", WMCTRL).with("xprop", "WM_CLASS(STRING) = \"gl\", \"mpv\"\n");
+        let class = |class: &str| WindowMatch::Class(class.to_owned());
+        assert_eq!(find_window(&runner, SessionType::X11, &class("mpv"), 9876).unwrap(), Some("0x03a00006".to_owned()));
+        assert_eq!(find_window(&runner, SessionType::X11, &class("Mpv"), 9876).unwrap(), None);
+        assert!(runner.calls().contains(&"xprop -id 0x03a00006 WM_CLASS".to_owned()));
+    }
+
+    #[test]
+    fn active_window_is_compared_without_padding() {
+        let runner = MockRunner::new().with("xprop", "_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00006\n");
+        assert!(is_window_focused(&runner, SessionType::X11, "0x03a00006").unwrap());
+        assert!(!is_window_focused(&runner, SessionType::X11, "0x02200003").unwrap());
+        assert_eq!(parse_active_window("_NET_ACTIVE_WINDOW:  not found.\n"), None);
+    }
+}